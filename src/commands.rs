@@ -0,0 +1,243 @@
+//! Command implementations shared by the CLI and the TUI
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+use crate::{
+    common::{self, Progress},
+    config::{Config, Package},
+    detect::{self, Platform},
+    forge::{Asset, Error, GitHub, Release, ReleaseSource, RepoSpec, RepoSummary},
+    store::Store,
+};
+
+/// Latest releases looked up at once while listing starred repositories
+const LOOKUPS: usize = 8;
+
+/// The package entry for `OWNER REPO [RELEASE] [ASSET] [INSTALL-CMD]`,
+/// named by [`RepoSpec::package_name`]
+pub fn package(
+    owner: &str,
+    repo: &str,
+    release: Option<&str>,
+    asset: Option<&str>,
+    install: Option<&str>,
+) -> Result<(String, Package), Error> {
+    let spec = format!("{}/{}", owner, repo).parse::<RepoSpec>()?;
+    let pkg = Package {
+        repo: spec.to_string(),
+        release: release.filter(|r| *r != "latest").map(str::to_owned),
+        asset: asset.filter(|a| *a != "all").map(str::to_owned),
+        install: install.map(str::to_owned),
+        bin: vec![],
+    };
+    Ok((spec.package_name(), pkg))
+}
+
+pub fn global_store() -> Result<Store, Error> {
+    Store::global().ok_or_else(|| "No data directory to put the global store in".into())
+}
+
+/// Install into the global store, returns the installed tag
+pub async fn install(name: &str, pkg: &Package, progress: Progress) -> Result<String, Error> {
+    global_store()?.install(name, pkg, progress).await
+}
+
+/// Download `asset` of `repo` to `location`, or into it if it is a directory
+pub async fn download(
+    repo: &str,
+    asset: &Asset,
+    location: &Path,
+    progress: Progress,
+) -> Result<PathBuf, Error> {
+    let path = if location.is_dir() {
+        location.join(&asset.name)
+    } else {
+        location.to_owned()
+    };
+    // Through the forge, a private repository's assets need its token
+    let src = repo.parse::<RepoSpec>()?.source()?;
+    let bytes = src.download_asset(asset, progress).await?;
+    std::fs::write(&path, bytes)?;
+    Ok(path)
+}
+
+/// Add (or replace) a package entry in a config file, the global package file by default
+pub fn save(config: Option<&Path>, name: &str, pkg: Package) -> Result<PathBuf, Error> {
+    let path = match config {
+        Some(path) => path.to_owned(),
+        None => Config::global_path().ok_or("No config directory to save to")?,
+    };
+    let mut conf = if path.exists() {
+        Config::load(&path)?
+    } else {
+        Config::default()
+    };
+    conf.packages.insert(name.to_owned(), pkg);
+    conf.save(&path)?;
+    Ok(path)
+}
+
+/// The package entry of an installed package, from the global package file or the store
+pub fn installed_package(name: &str) -> Result<Package, Error> {
+    let configured = Config::load_global()?.packages.remove(name);
+    configured
+        .or_else(|| global_store().ok()?.package(name))
+        .ok_or_else(|| format!("No package {} in the global package file or store", name).into())
+}
+
+/// The releases after one tag up to and including another
+#[derive(Debug, Clone, PartialEq)]
+pub struct Changelog {
+    pub from: String,
+    pub to: String,
+    /// Oldest first
+    pub releases: Vec<Release>,
+    /// The forge's page comparing `from` and `to`
+    pub compare: Option<Url>,
+}
+
+impl Changelog {
+    /// The notes of every release as one Markdown document, under a heading per release
+    pub fn markdown(&self, with_compare: bool) -> String {
+        let mut md = String::new();
+        if let (true, Some(url)) = (with_compare, &self.compare) {
+            md.push_str(&format!(
+                "[Compare {}...{}]({})\n\n",
+                self.from, self.to, url
+            ));
+        }
+        for rel in &self.releases {
+            md.push_str(&format!(
+                "# {}\n\n",
+                rel.name.as_deref().unwrap_or(&rel.tag_name)
+            ));
+            md.push_str(rel.body.as_deref().unwrap_or("No release notes").trim());
+            md.push_str("\n\n");
+        }
+        if self.releases.is_empty() {
+            md.push_str(&format!(
+                "No releases after {} up to {}\n",
+                self.from, self.to
+            ));
+        }
+        md
+    }
+}
+
+/// The releases of `repo` after `from` up to `to`, the latest release by default.
+/// Pre-releases are left out unless `to` is one
+pub async fn changelog(repo: &str, from: &str, to: Option<&str>) -> Result<Changelog, Error> {
+    let spec = repo.parse::<RepoSpec>()?;
+    let src = spec.source()?;
+    let to = match to {
+        Some(to) => to.to_owned(),
+        None => src.get_latest().await?.ok_or("No latest release")?.tag_name,
+    };
+    let all = src.list_releases().await?;
+    let prerelease = all.iter().any(|rel| rel.tag_name == to && rel.prerelease);
+    let mut releases = all
+        .into_iter()
+        .filter(|rel| !rel.draft && (prerelease || !rel.prerelease))
+        .filter(|rel| {
+            common::compare_tags(&rel.tag_name, from) == Ordering::Greater
+                && common::compare_tags(&rel.tag_name, &to) != Ordering::Greater
+        })
+        .collect::<Vec<_>>();
+    releases.sort_by(|a, b| common::compare_tags(&a.tag_name, &b.tag_name));
+    Ok(Changelog {
+        compare: spec.compare_url(from, &to).ok(),
+        from: from.to_owned(),
+        to,
+        releases,
+    })
+}
+
+/// A starred or watched repository whose newest release with assets for this platform was found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installable {
+    pub summary: RepoSummary,
+    pub tag: String,
+    /// The assets of that release that fit this platform
+    pub assets: Vec<Asset>,
+}
+
+impl Installable {
+    /// The package entry pinning that release and its first fitting asset
+    pub fn package(&self) -> Result<(String, Package), Error> {
+        let asset = self.assets.first().ok_or("No asset for this platform")?;
+        package(
+            &self.summary.owner,
+            &self.summary.repo,
+            Some(&format!("t:{}", self.tag)),
+            Some(&format!("^{}$", regex::escape(&asset.name))),
+            None,
+        )
+    }
+}
+
+/// The github.com repositories starred, or watched, by the user of the token that can be installed here
+pub async fn installable(watched: bool) -> Result<Vec<Installable>, Error> {
+    let token = GitHub::token().ok_or(
+        "Listing starred repositories needs GITHUB_TOKEN or a token in [hosts.\"github.com\"]",
+    )?;
+    let user = GitHub::user(token)?;
+    let repos = if watched {
+        user.watched().await?
+    } else {
+        user.starred().await?
+    };
+    let host = Platform::current();
+    let user = &user;
+    let found: Vec<Option<Installable>> = stream::iter(repos)
+        .map(|summary| async move {
+            let src = user.repo(&summary.owner, &summary.repo);
+            let found = fitting_release(&src, &host).await?;
+            Ok::<_, Error>(found.map(|(tag, assets)| Installable {
+                summary,
+                tag,
+                assets,
+            }))
+        })
+        .buffered(LOOKUPS)
+        .try_collect()
+        .await?;
+    Ok(found.into_iter().flatten().collect())
+}
+
+/// The newest published release with assets for `host`, and those assets.
+/// Pages are fetched until one has such a release
+pub async fn fitting_release(
+    src: &dyn ReleaseSource,
+    host: &Platform,
+) -> Result<Option<(String, Vec<Asset>)>, Error> {
+    for page in 1.. {
+        let rels = src.list_releases_page(page, 100).await?;
+        if rels.is_empty() {
+            break;
+        }
+        for rel in rels.into_iter().filter(|rel| !rel.draft && !rel.prerelease) {
+            let assets = rel
+                .assets
+                .into_iter()
+                .filter(|ass| !detect::is_checksum_or_signature(&ass.name))
+                .filter(|ass| Platform::from_name(&ass.name).fits(host))
+                .collect::<Vec<_>>();
+            if !assets.is_empty() {
+                return Ok(Some((rel.tag_name, assets)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Expand a leading `~/`
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
@@ -1,87 +1,97 @@
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 use itertools::Itertools;
-use octocrab::{
-    self,
-    models::{
-        repos::{Asset, Release},
-        AssetId,
-    },
-    repos::ReleasesHandler,
-    Error,
-};
 use regex::Regex;
+use std::{
+    cmp::{Ordering, Reverse},
+    iter,
+    str::FromStr,
+};
+use url::Url;
 
-#[derive(Debug, Clone)]
+use crate::forge::{Asset, AssetId, Error, Release, ReleaseSource};
+
+#[derive(Debug, Clone, Default)]
 pub enum ReleaseFinder {
+    #[default]
     Latest,
     ByTag(String),
     ByRegex(Regex),
+    /// The best fuzzy match for the tag
+    Fuzzy(String),
 }
 
 impl ReleaseFinder {
-    async fn find(&self, rels: ReleasesHandler<'_, '_>) -> Result<Option<Release>, Error> {
+    pub async fn find(&self, src: &dyn ReleaseSource) -> Result<Option<Release>, Error> {
         match self {
-            ReleaseFinder::Latest => rels.get_latest().await.map(Some),
-            // TODO: handle missing release in the by tag
-            ReleaseFinder::ByTag(tag) => rels.get_by_tag(&tag).await.map(Some),
+            ReleaseFinder::Latest => src.get_latest().await,
+            ReleaseFinder::ByTag(tag) => src.get_by_tag(tag).await,
             ReleaseFinder::ByRegex(re) => {
-                let mut current_page = rels.list().per_page(100).page(0u32).send().await?;
-                let mut prs = current_page.take_items();
-
-                let inst = octocrab::instance();
-                while let Ok(Some(mut new_page)) = inst.get_page(&current_page.next).await {
-                    prs.extend(new_page.take_items());
-
-                    for page in prs.drain(..) {
-                        if re.is_match(&page.tag_name) {
-                            return Ok(Some(page));
-                        }
+                // Stop fetching as soon as a page has a match
+                for page in 1.. {
+                    let rels = src.list_releases_page(page, 100).await?;
+                    if rels.is_empty() {
+                        break;
+                    }
+                    if let Some(rel) = rels.into_iter().find(|rel| re.is_match(&rel.tag_name)) {
+                        return Ok(Some(rel));
                     }
                 }
 
                 Ok(None)
-                // Ok(prs.into_iter().findl(|x| re.is_match(&x.tag_name)))
             }
-        }
-    }
-    fn find_from(&self, rels: Vec<Release>) -> Vec<Release> {
-        match self {
-            ReleaseFinder::Latest => vec![rels[0].clone()],
-            ReleaseFinder::ByTag(tag) => rels
-                .iter()
-                .cloned()
-                .filter(|rel| &rel.tag_name == tag)
-                .collect_vec(),
-            ReleaseFinder::ByRegex(re) => rels
-                .iter()
-                .cloned()
-                .filter(|rel| re.is_match(&rel.tag_name))
-                .collect_vec(),
+            ReleaseFinder::Fuzzy(pattern) => {
+                // Rank every page, nearly any page has some weak match that would hide a better
+                // one further on. Only the exact tag ends the search early
+                let mut all = Vec::new();
+                for page in 1.. {
+                    let rels = src.list_releases_page(page, 100).await?;
+                    if rels.is_empty() {
+                        break;
+                    }
+                    if let Some(rel) = rels.iter().find(|rel| rel.tag_name == *pattern) {
+                        return Ok(Some(rel.clone()));
+                    }
+                    all.extend(rels);
+                }
+
+                let best = find_release_fuzzy(pattern, &all).into_iter().next();
+                Ok(best.map(|(rel, _)| rel))
+            }
         }
     }
 }
 
-impl Default for ReleaseFinder {
-    fn default() -> Self {
-        Self::Latest
+impl FromStr for ReleaseFinder {
+    type Err = regex::Error;
+
+    /// Parses the `[RELEASE]` selector syntax accepted on the command line
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "latest" => ReleaseFinder::Latest,
+            s if s.starts_with("t:") => ReleaseFinder::ByTag(s[2..].to_owned()),
+            s if s.starts_with("f:") => ReleaseFinder::Fuzzy(s[2..].to_owned()),
+            s => ReleaseFinder::ByRegex(Regex::new(s)?),
+        })
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum AssetFinder {
+    // The CLI and TUI take every matching asset through `Package::find_assets` instead
+    #[cfg_attr(not(test), allow(dead_code))]
     ByRegex(ReleaseFinder, Regex),
     ById(AssetId),
 }
 
 impl AssetFinder {
-    async fn find(&self, rels: ReleasesHandler<'_, '_>) -> Result<Option<Asset>, Error> {
+    pub async fn find(&self, src: &dyn ReleaseSource) -> Result<Option<Asset>, Error> {
         Ok(match self {
             AssetFinder::ByRegex(relfin, re) => {
-                let rel = relfin.find(rels).await?;
+                let rel = relfin.find(src).await?;
                 eprintln!("search through {:#?}", rel);
                 rel.and_then(|rel| rel.assets.into_iter().find(|x| re.is_match(&x.name)))
             }
-            // TODO: handle missing asset in the by id (convert to option)
-            &AssetFinder::ById(id) => Some(rels.get_asset(id).await?),
+            &AssetFinder::ById(id) => src.get_asset(id).await?,
         })
     }
 }
@@ -101,53 +111,141 @@ pub fn find_asset_from(re: &Regex, assets: &[Asset]) -> Vec<Asset> {
         .collect_vec()
 }
 
-pub async fn list_releases_page(
-    user: &str,
-    repo: &str,
-    page: u32,
-    per: u8,
-) -> Result<Vec<Release>, Error> {
-    let inst = octocrab::instance();
-    let repos = inst.repos(user, repo);
-    let rels = repos.releases();
-    Ok(rels
-        .list()
-        .per_page(per.min(100))
-        .page(page)
-        .send()
-        .await?
-        .take_items())
-}
-pub async fn list_releases(user: &str, repo: &str) -> Result<Vec<Release>, Error> {
-    let inst = octocrab::instance();
-    let repos = inst.repos(user, repo);
-    let rels = repos.releases();
-    let mut current_page = rels.list().per_page(100).page(0u32).send().await?;
-    let mut prs = current_page.take_items();
-    while let Ok(Some(mut new_page)) = inst.get_page(&current_page.next).await {
-        prs.extend(new_page.take_items());
+/// Items whose name fuzzy matches `pattern`, best first, with the indices of the matched characters.
+/// An empty pattern matches everything in the original order
+pub fn fuzzy_rank<T: Clone>(
+    pattern: &str,
+    items: &[T],
+    name: impl Fn(&T) -> &str,
+) -> Vec<(T, Vec<usize>)> {
+    if pattern.is_empty() {
+        return items
+            .iter()
+            .map(|item| (item.clone(), Vec::new()))
+            .collect();
     }
-    Ok(prs)
+    let matcher = SkimMatcherV2::default();
+    items
+        .iter()
+        .filter_map(|item| {
+            let (score, hits) = matcher.fuzzy_indices(name(item), pattern)?;
+            Some((score, item.clone(), hits))
+        })
+        // Stable, so equally good matches stay newest first
+        .sorted_by_key(|(score, _, _)| Reverse(*score))
+        .map(|(_, item, hits)| (item, hits))
+        .collect()
+}
+pub fn find_release_fuzzy(pattern: &str, rels: &[Release]) -> Vec<(Release, Vec<usize>)> {
+    fuzzy_rank(pattern, rels, |rel| &rel.tag_name)
+}
+pub fn find_asset_fuzzy(pattern: &str, assets: &[Asset]) -> Vec<(Asset, Vec<usize>)> {
+    fuzzy_rank(pattern, assets, |ass| &ass.name)
 }
-pub async fn find_release(
-    user: &str,
-    repo: &str,
-    find: ReleaseFinder,
-) -> Result<Option<Release>, Error> {
-    let inst = octocrab::instance();
-    let repos = inst.repos(user, repo);
-    let rels = repos.releases();
 
-    find.find(rels).await
+/// Orders tags as versions, `v1.10.0` after `v1.9.2` and `1.0.0-rc.1` before `1.0.0`.
+/// Anything before the first digit is ignored, eg. `v` or `release-`
+pub fn compare_tags(a: &str, b: &str) -> Ordering {
+    let (a_core, a_pre) = split_version(a);
+    let (b_core, b_pre) = split_version(b);
+    let len = a_core.len().max(b_core.len());
+    let pad = |core: Vec<u64>| core.into_iter().chain(iter::repeat(0)).take(len);
+    pad(a_core)
+        .cmp(pad(b_core))
+        // A pre-release comes before the release itself
+        .then_with(|| match (a_pre.is_empty(), b_pre.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => a_pre.cmp(b_pre),
+        })
+        .then_with(|| a.cmp(b))
 }
-pub async fn find_asset(user: &str, repo: &str, find: AssetFinder) -> Result<Option<Asset>, Error> {
-    let inst = octocrab::instance();
-    let repos = inst.repos(user, repo);
-    let rels = repos.releases();
 
-    find.find(rels).await
+/// `v1.2.3-rc.1` into `[1, 2, 3]` and `rc.1`
+fn split_version(tag: &str) -> (Vec<u64>, &str) {
+    let start = tag.find(|c: char| c.is_ascii_digit()).unwrap_or(tag.len());
+    let version = &tag[start..];
+    let end = version
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(version.len());
+    let core = version[..end]
+        .split('.')
+        .filter_map(|n| n.parse().ok())
+        .collect();
+    (
+        core,
+        version[end..].trim_start_matches(&['-', '+', '.'][..]),
+    )
 }
 
-pub async fn download_asset(asset: Asset) -> Result<Vec<u8>, Error> {
-    todo!("Choose how to downlod")
+/// Redirects followed by [`download`] before giving up
+const MAX_REDIRECTS: usize = 10;
+
+/// Called with the bytes downloaded so far and the total size if known.
+/// Returning `false` cancels the download
+pub type Progress = Box<dyn FnMut(u64, Option<u64>) -> bool + Send>;
+
+pub fn no_progress() -> Progress {
+    Box::new(|_, _| true)
+}
+
+pub async fn download_asset(asset: Asset, progress: Progress) -> Result<Vec<u8>, Error> {
+    download(asset.browser_download_url, Vec::new(), progress).await
+}
+
+/// Download `url` with extra request headers, eg. a token for a private repository
+pub async fn download(
+    url: Url,
+    headers: Vec<String>,
+    progress: Progress,
+) -> Result<Vec<u8>, Error> {
+    Ok(tokio::task::spawn_blocking(move || download_url(url, &headers, progress)).await??)
+}
+
+/// Blocking download of the whole body, following the redirect to the asset storage.
+/// `headers` only go to the host of `url`, the storage is usually elsewhere and must not see a token
+fn download_url(
+    mut url: Url,
+    headers: &[String],
+    mut progress: Progress,
+) -> Result<Vec<u8>, String> {
+    let origin = url.origin();
+    for _ in 0..MAX_REDIRECTS {
+        let mut buf = Vec::new();
+        let mut easy = curl::easy::Easy::new();
+        let curl = |e: curl::Error| format!("{}: {}", url, e);
+        easy.url(url.as_str()).map_err(curl)?;
+        easy.fail_on_error(true).map_err(curl)?;
+        easy.useragent("grpm").map_err(curl)?;
+        easy.progress(true).map_err(curl)?;
+        if url.origin() == origin {
+            let mut list = curl::easy::List::new();
+            for header in headers {
+                list.append(header).map_err(curl)?;
+            }
+            easy.http_headers(list).map_err(curl)?;
+        }
+        {
+            let mut transfer = easy.transfer();
+            transfer
+                .write_function(|data| {
+                    buf.extend_from_slice(data);
+                    Ok(data.len())
+                })
+                .map_err(curl)?;
+            // curl reports a total of 0 until it knows the size
+            transfer
+                .progress_function(|total, now, _, _| {
+                    progress(now as u64, Some(total as u64).filter(|&t| t > 0))
+                })
+                .map_err(curl)?;
+            transfer.perform().map_err(curl)?;
+        }
+        match easy.redirect_url().map_err(curl)? {
+            Some(next) => url = Url::parse(next).map_err(|e| format!("{}: {}", next, e))?,
+            None => return Ok(buf),
+        }
+    }
+    Err(format!("{}: too many redirects", url))
 }
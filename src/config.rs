@@ -0,0 +1,164 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+use crate::{
+    common::{self, ReleaseFinder},
+    forge::Asset,
+};
+
+/// A single package entry, shared by the global package file and project manifests
+///
+/// ```toml
+/// [packages.rg]
+/// repo = "BurntSushi/ripgrep"
+/// release = "t:13.0.0"
+/// asset = "x86_64-unknown-linux-musl"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Package {
+    /// `OWNER/REPO`, or any other repository accepted by [`RepoSpec`](crate::forge::RepoSpec)
+    pub repo: String,
+    /// Same syntax as the `[RELEASE]` argument, defaults to `latest`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release: Option<String>,
+    /// Regex for the asset to download, the first match is used.
+    /// `f:PATTERN` fuzzy matches instead and uses the best match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    /// Shell command run inside the install directory after unpacking
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install: Option<String>,
+    /// Executables to expose, relative to the install directory. Defaults to every executable file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bin: Vec<String>,
+}
+
+impl Package {
+    pub fn release_finder(&self) -> Result<ReleaseFinder, regex::Error> {
+        self.release.as_deref().unwrap_or("latest").parse()
+    }
+    pub fn asset_re(&self) -> Result<Regex, regex::Error> {
+        Regex::new(self.asset.as_deref().unwrap_or(".*"))
+    }
+    /// The assets matching `asset`, best first
+    pub fn find_assets(&self, assets: &[Asset]) -> Result<Vec<Asset>, regex::Error> {
+        Ok(
+            match self.asset.as_deref().and_then(|a| a.strip_prefix("f:")) {
+                Some(pattern) => common::find_asset_fuzzy(pattern, assets)
+                    .into_iter()
+                    .map(|(ass, _)| ass)
+                    .collect(),
+                None => common::find_asset_from(&self.asset_re()?, assets),
+            },
+        )
+    }
+}
+
+/// Settings for a self-hosted forge, keyed by host name
+///
+/// ```toml
+/// [hosts."ghe.example.com"]
+/// api = "https://ghe.example.com/api/v3/"
+/// upload = "https://ghe.example.com/api/uploads/"
+/// token_env = "GHE_TOKEN"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostConfig {
+    /// API root, defaults to `https://HOST/api/v3/` for GitHub Enterprise
+    /// and `https://api.github.com/` for github.com
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api: Option<Url>,
+    /// Upload API root, defaults to `https://HOST/api/uploads/`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload: Option<Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Environment variable to read the token from, to keep it out of the file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+}
+
+impl HostConfig {
+    pub fn token(&self) -> Option<String> {
+        self.token
+            .clone()
+            .or_else(|| std::env::var(self.token_env.as_ref()?).ok())
+    }
+}
+
+/// Key bindings and colours of the TUI
+///
+/// ```toml
+/// [tui]
+/// keymap = "vim"
+///
+/// [tui.keys]
+/// quit = "q ctrl-c"
+///
+/// [tui.theme]
+/// button = "black on cyan bold"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TuiConfig {
+    /// `default` or `vim`, see [`Keymap`](crate::keymap::Keymap)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keymap: Option<String>,
+    /// Action names to space separated keys, replacing the keymap's keys for that action
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, String>,
+    /// Style names to styles, see [`Theme`](crate::theme::Theme)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub theme: BTreeMap<String, String>,
+}
+
+impl TuiConfig {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub packages: BTreeMap<String, Package>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hosts: BTreeMap<String, HostConfig>,
+    #[serde(default, skip_serializing_if = "TuiConfig::is_empty")]
+    pub tui: TuiConfig,
+}
+
+impl Config {
+    pub fn parse(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let s = fs::read_to_string(path)?;
+        Self::parse(&s).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// `~/.config/grpm/packages.toml`
+    pub fn global_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("grpm").join("packages.toml"))
+    }
+    /// The global package file, or an empty config if there is none yet
+    pub fn load_global() -> Result<Self, Box<dyn std::error::Error>> {
+        match Self::global_path() {
+            Some(path) if path.exists() => Self::load(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+}
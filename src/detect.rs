@@ -0,0 +1,245 @@
+//! What can be told about an asset from its name and the other assets of its release
+use std::{env, fmt};
+
+use crate::forge::Asset;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Tar,
+    TarGz,
+    TarXz,
+    TarBz2,
+    TarZst,
+    Zip,
+    Gzip,
+    Xz,
+    Deb,
+    Rpm,
+    AppImage,
+    Dmg,
+    Msi,
+    Exe,
+}
+
+/// Longer extensions first so `.tar.gz` is not taken for `.gz`
+const EXTENSIONS: [(&str, Format); 17] = [
+    (".tar.gz", Format::TarGz),
+    (".tgz", Format::TarGz),
+    (".tar.xz", Format::TarXz),
+    (".txz", Format::TarXz),
+    (".tar.bz2", Format::TarBz2),
+    (".tbz", Format::TarBz2),
+    (".tar.zst", Format::TarZst),
+    (".tar", Format::Tar),
+    (".zip", Format::Zip),
+    (".gz", Format::Gzip),
+    (".xz", Format::Xz),
+    (".deb", Format::Deb),
+    (".rpm", Format::Rpm),
+    (".appimage", Format::AppImage),
+    (".dmg", Format::Dmg),
+    (".msi", Format::Msi),
+    (".exe", Format::Exe),
+];
+
+impl Format {
+    /// `None` for anything without a known extension, usually a bare executable
+    pub fn from_name(name: &str) -> Option<Self> {
+        let lower = name.to_lowercase();
+        EXTENSIONS
+            .iter()
+            .find(|(ext, _)| lower.ends_with(ext))
+            .map(|&(_, format)| format)
+    }
+    pub fn is_tar(self) -> bool {
+        matches!(
+            self,
+            Format::Tar | Format::TarGz | Format::TarXz | Format::TarBz2 | Format::TarZst
+        )
+    }
+    /// Implied by the format alone, eg. `.msi` is only for Windows
+    fn os(self) -> Option<&'static str> {
+        match self {
+            Format::Deb | Format::Rpm | Format::AppImage => Some("linux"),
+            Format::Dmg => Some("macos"),
+            Format::Msi | Format::Exe => Some("windows"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Tar => "tar archive",
+            Format::TarGz => "gzipped tar archive",
+            Format::TarXz => "xz tar archive",
+            Format::TarBz2 => "bzip2 tar archive",
+            Format::TarZst => "zstd tar archive",
+            Format::Zip => "zip archive",
+            Format::Gzip => "gzip",
+            Format::Xz => "xz",
+            Format::Deb => "Debian package",
+            Format::Rpm => "RPM package",
+            Format::AppImage => "AppImage",
+            Format::Dmg => "macOS disk image",
+            Format::Msi => "Windows installer",
+            Format::Exe => "Windows executable",
+        })
+    }
+}
+
+/// Target guessed from the usual naming schemes, eg. `x86_64-unknown-linux-musl` or `linux_amd64`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Platform {
+    pub os: Option<&'static str>,
+    pub arch: Option<&'static str>,
+    pub libc: Option<&'static str>,
+}
+
+// Keys of up to 4 characters only match a whole word of the name, longer ones anywhere in it
+const OSES: [(&str, &str); 14] = [
+    ("linux", "linux"),
+    ("darwin", "macos"),
+    ("macos", "macos"),
+    ("apple", "macos"),
+    ("osx", "macos"),
+    ("mac", "macos"),
+    ("windows", "windows"),
+    ("win64", "windows"),
+    ("win32", "windows"),
+    ("win", "windows"),
+    ("freebsd", "freebsd"),
+    ("netbsd", "netbsd"),
+    ("openbsd", "openbsd"),
+    ("android", "android"),
+];
+const ARCHES: [(&str, &str); 15] = [
+    ("x86_64", "x86_64"),
+    ("x86-64", "x86_64"),
+    ("amd64", "x86_64"),
+    ("x64", "x86_64"),
+    ("aarch64", "aarch64"),
+    ("arm64", "aarch64"),
+    ("armv7", "armv7"),
+    ("armhf", "armv7"),
+    ("armv6", "armv6"),
+    ("arm", "arm"),
+    ("i686", "x86"),
+    ("i386", "x86"),
+    ("386", "x86"),
+    ("x86", "x86"),
+    ("riscv64", "riscv64"),
+];
+const LIBCS: [(&str, &str); 3] = [("musl", "musl"), ("gnu", "gnu"), ("msvc", "msvc")];
+
+impl Platform {
+    pub fn from_name(name: &str) -> Self {
+        let lower = name.to_lowercase();
+        let words = lower
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .collect::<Vec<_>>();
+        let find = |keys: &[(&str, &'static str)]| {
+            keys.iter()
+                .find(|(key, _)| {
+                    if key.len() > 4 {
+                        lower.contains(key)
+                    } else {
+                        words.contains(key)
+                    }
+                })
+                .map(|&(_, value)| value)
+        };
+        Self {
+            os: find(&OSES).or_else(|| Format::from_name(name)?.os()),
+            arch: find(&ARCHES),
+            libc: find(&LIBCS),
+        }
+    }
+    pub fn is_unknown(&self) -> bool {
+        *self == Self::default()
+    }
+    /// The platform grpm was built for
+    pub fn current() -> Self {
+        let os = match env::consts::OS {
+            "macos" | "ios" => "macos",
+            os => os,
+        };
+        let arch = match env::consts::ARCH {
+            "x86_64" => Some("x86_64"),
+            "aarch64" => Some("aarch64"),
+            "arm" => Some("armv7"),
+            "x86" => Some("x86"),
+            "riscv64" => Some("riscv64"),
+            _ => None,
+        };
+        Self {
+            os: OSES.iter().map(|&(_, o)| o).find(|&o| o == os),
+            arch,
+            libc: None,
+        }
+    }
+    /// Whether an asset named for this platform runs on `host`, an unnamed architecture is taken to fit
+    pub fn fits(&self, host: &Self) -> bool {
+        self.os.is_some()
+            && self.os == host.os
+            && (self.arch.is_none() || host.arch.is_none() || self.arch == host.arch)
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [self.os, self.arch, self.libc];
+        let parts = parts.iter().flatten().copied().collect::<Vec<_>>();
+        f.write_str(&parts.join(" "))
+    }
+}
+
+const CHECKSUM_SUFFIXES: [&str; 6] = [
+    ".sha256",
+    ".sha256sum",
+    ".sha512",
+    ".sha512sum",
+    ".sha1",
+    ".md5",
+];
+const SIGNATURE_SUFFIXES: [&str; 5] = [".asc", ".sig", ".minisig", ".pem", ".gpg"];
+
+/// Assets next to `asset` with checksums for it: `ASSET.sha256` and the like, or a list such as `SHA256SUMS`
+pub fn checksums<'a>(asset: &Asset, siblings: &'a [Asset]) -> Vec<&'a Asset> {
+    siblings
+        .iter()
+        .filter(|other| {
+            let lower = other.name.to_lowercase();
+            suffixed(&asset.name, &other.name, &CHECKSUM_SUFFIXES)
+                || lower.contains("checksums")
+                || lower.contains("sha256sums")
+                || lower.contains("sha512sums")
+        })
+        .collect()
+}
+
+/// Assets next to `asset` that sign it, `ASSET.asc`, `ASSET.sig` and the like
+pub fn signatures<'a>(asset: &Asset, siblings: &'a [Asset]) -> Vec<&'a Asset> {
+    siblings
+        .iter()
+        .filter(|other| suffixed(&asset.name, &other.name, &SIGNATURE_SUFFIXES))
+        .collect()
+}
+
+/// A checksum or signature for another asset rather than something to install
+pub fn is_checksum_or_signature(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    CHECKSUM_SUFFIXES
+        .iter()
+        .chain(&SIGNATURE_SUFFIXES)
+        .any(|suffix| lower.ends_with(suffix))
+}
+
+fn suffixed(name: &str, other: &str, suffixes: &[&str]) -> bool {
+    other.strip_prefix(name).is_some_and(|rest| {
+        suffixes
+            .iter()
+            .any(|suffix| rest.eq_ignore_ascii_case(suffix))
+    })
+}
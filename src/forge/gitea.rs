@@ -0,0 +1,141 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use url::Url;
+
+use super::{get_json, Asset, AssetId, Error, Release, ReleaseSource};
+use crate::common::{self, Progress};
+
+/// Gitea and Forgejo's v1 releases API, codeberg.org or a self-hosted instance.
+/// `GITEA_TOKEN` is sent if set, with API requests and downloads from the instance, for private repositories
+#[derive(Debug, Clone)]
+pub struct Gitea {
+    client: reqwest::Client,
+    /// `.../api/v1/repos/OWNER/REPO`
+    repo_url: Url,
+    token: Option<String>,
+}
+
+impl Gitea {
+    /// `token` overrides the environment variable
+    pub fn new(host: Option<Url>, token: Option<String>, owner: &str, repo: &str) -> Self {
+        let mut repo_url =
+            host.unwrap_or_else(|| Url::parse("https://codeberg.org").expect("valid url"));
+        repo_url
+            .path_segments_mut()
+            .expect("http url")
+            .pop_if_empty()
+            .extend(&["api", "v1", "repos", owner, repo]);
+        Self {
+            client: reqwest::Client::new(),
+            repo_url,
+            token: token.or_else(|| std::env::var("GITEA_TOKEN").ok()),
+        }
+    }
+
+    fn get(&self, path: &[&str]) -> reqwest::RequestBuilder {
+        let mut url = self.repo_url.clone();
+        url.path_segments_mut()
+            .expect("http url")
+            .push("releases")
+            .extend(path);
+        let req = self.client.get(url);
+        match &self.token {
+            Some(token) => req.header("Authorization", format!("token {}", token)),
+            None => req,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GtRelease {
+    tag_name: String,
+    name: Option<String>,
+    body: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    published_at: Option<DateTime<Utc>>,
+    html_url: Option<Url>,
+    #[serde(default)]
+    assets: Vec<GtAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GtAsset {
+    id: u64,
+    name: String,
+    size: Option<u64>,
+    download_count: Option<u64>,
+    created_at: Option<DateTime<Utc>>,
+    browser_download_url: Url,
+}
+
+impl From<GtRelease> for Release {
+    fn from(rel: GtRelease) -> Self {
+        Self {
+            tag_name: rel.tag_name,
+            name: rel.name,
+            body: rel.body,
+            draft: rel.draft,
+            prerelease: rel.prerelease,
+            published_at: rel.published_at,
+            html_url: rel.html_url,
+            assets: rel
+                .assets
+                .into_iter()
+                .map(|ass| Asset {
+                    id: ass.id,
+                    name: ass.name,
+                    label: None,
+                    content_type: None,
+                    size: ass.size,
+                    download_count: ass.download_count,
+                    uploader: None,
+                    created_at: ass.created_at,
+                    updated_at: None,
+                    browser_download_url: ass.browser_download_url,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl ReleaseSource for Gitea {
+    async fn list_releases_page(&self, page: u32, per: u8) -> Result<Vec<Release>, Error> {
+        let req = self
+            .get(&[])
+            .query(&[("page", page.to_string()), ("limit", per.to_string())]);
+        let rels: Option<Vec<GtRelease>> = get_json(req).await?;
+        Ok(rels
+            .unwrap_or_default()
+            .into_iter()
+            .map(Release::from)
+            .collect())
+    }
+    async fn get_latest(&self) -> Result<Option<Release>, Error> {
+        let rel: Option<GtRelease> = get_json(self.get(&["latest"])).await?;
+        Ok(rel.map(Release::from))
+    }
+    async fn get_by_tag(&self, tag: &str) -> Result<Option<Release>, Error> {
+        let rel: Option<GtRelease> = get_json(self.get(&["tags", tag])).await?;
+        Ok(rel.map(Release::from))
+    }
+    async fn get_asset(&self, id: AssetId) -> Result<Option<Asset>, Error> {
+        // Attachments are only looked up by id within their release
+        super::find_asset_by_id(self, id).await
+    }
+    async fn download_asset(&self, asset: &Asset, progress: Progress) -> Result<Vec<u8>, Error> {
+        let url = &asset.browser_download_url;
+        match &self.token {
+            // Attachment downloads accept the API token, links to other hosts don't get it
+            Some(token) if url.origin() == self.repo_url.origin() => {
+                let headers = vec![format!("Authorization: token {}", token)];
+                common::download(url.clone(), headers, progress).await
+            }
+            _ => common::download_asset(asset.clone(), progress).await,
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use async_trait::async_trait;
+use octocrab::{
+    models::{repos as gh, AssetId, Repository},
+    Octocrab,
+};
+use std::{convert::TryFrom, env, sync::Arc};
+use url::Url;
+
+use super::{Asset, Error, Release, ReleaseSource, RepoSummary};
+use crate::{
+    common::{self, Progress},
+    config::{Config, HostConfig},
+};
+
+/// github.com through the global `octocrab` instance, or a GitHub Enterprise Server
+#[derive(Debug, Clone)]
+pub struct GitHub {
+    client: Arc<Octocrab>,
+    /// API root and token of a GitHub Enterprise Server, `None` for github.com
+    enterprise: Option<(Url, Option<String>)>,
+    owner: String,
+    repo: String,
+}
+
+impl GitHub {
+    pub fn new(owner: &str, repo: &str) -> Self {
+        Self {
+            client: octocrab::instance(),
+            enterprise: None,
+            owner: owner.to_owned(),
+            repo: repo.to_owned(),
+        }
+    }
+    /// A GitHub Enterprise Server at `host`, configured by its `[hosts]` entry if any
+    pub fn enterprise(
+        host: &Url,
+        conf: Option<&HostConfig>,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Self, Error> {
+        let api = match conf.and_then(|c| c.api.clone()) {
+            Some(api) => api,
+            None => host.join("api/v3/")?,
+        };
+        let upload = match conf.and_then(|c| c.upload.clone()) {
+            Some(upload) => upload,
+            None => host.join("api/uploads/")?,
+        };
+        let mut builder = Octocrab::builder()
+            .base_url(api.clone())?
+            .upload_url(upload)?;
+        let token = conf.and_then(HostConfig::token);
+        if let Some(token) = &token {
+            builder = builder.personal_token(token.clone());
+        }
+        Ok(Self {
+            client: Arc::new(builder.build()?),
+            enterprise: Some((api, token)),
+            owner: owner.to_owned(),
+            repo: repo.to_owned(),
+        })
+    }
+    /// github.com as the user of `token`, for [`GitHub::starred`] and [`GitHub::watched`]
+    pub fn user(token: String) -> Result<Self, Error> {
+        Ok(Self {
+            client: Arc::new(Octocrab::builder().personal_token(token).build()?),
+            enterprise: None,
+            owner: String::new(),
+            repo: String::new(),
+        })
+    }
+    /// Another repository through the same client
+    pub fn repo(&self, owner: &str, repo: &str) -> Self {
+        Self {
+            client: self.client.clone(),
+            enterprise: self.enterprise.clone(),
+            owner: owner.to_owned(),
+            repo: repo.to_owned(),
+        }
+    }
+    /// Point the global `octocrab` instance at the `api` of github.com's `[hosts]` entry and
+    /// use its token, a stand-in API such as a test server can take github.com's place this way
+    pub fn initialise() -> Result<(), Error> {
+        let config = Config::load_global()?;
+        let conf = match config.hosts.get("github.com") {
+            Some(conf) => conf,
+            None => return Ok(()),
+        };
+        let mut builder = Octocrab::builder();
+        if let Some(api) = &conf.api {
+            builder = builder.base_url(api.clone())?;
+        }
+        if let Some(upload) = &conf.upload {
+            builder = builder.upload_url(upload.clone())?;
+        }
+        if let Some(token) = conf.token() {
+            builder = builder.personal_token(token);
+        }
+        octocrab::initialise(builder)?;
+        Ok(())
+    }
+    /// A token for github.com from its `[hosts]` entry, otherwise `GITHUB_TOKEN`
+    pub fn token() -> Option<String> {
+        Self::github_com().1
+    }
+    /// github.com's API root and token, from its `[hosts]` entry
+    fn github_com() -> (Url, Option<String>) {
+        let conf = Config::load_global()
+            .ok()
+            .and_then(|config| config.hosts.get("github.com").cloned())
+            .unwrap_or_default();
+        let api = conf
+            .api
+            .clone()
+            .unwrap_or_else(|| Url::parse("https://api.github.com/").expect("valid url"));
+        (api, conf.token().or_else(|| env::var("GITHUB_TOKEN").ok()))
+    }
+
+    /// Repositories the user starred, most recently starred first
+    pub async fn starred(&self) -> Result<Vec<RepoSummary>, Error> {
+        self.user_repos("user/starred").await
+    }
+    /// Repositories the user watches
+    pub async fn watched(&self) -> Result<Vec<RepoSummary>, Error> {
+        self.user_repos("user/subscriptions").await
+    }
+    async fn user_repos(&self, route: &str) -> Result<Vec<RepoSummary>, Error> {
+        let mut all = Vec::new();
+        for page in 1u32.. {
+            let repos: Vec<Repository> = self
+                .client
+                .get(route, Some(&[("per_page", 100), ("page", page)]))
+                .await?;
+            if repos.is_empty() {
+                break;
+            }
+            all.extend(repos.into_iter().map(RepoSummary::from_github));
+        }
+        Ok(all)
+    }
+
+    /// Search github.com repositories, best match first
+    pub async fn search(query: &str, per: u8) -> Result<Vec<RepoSummary>, Error> {
+        Ok(octocrab::instance()
+            .search()
+            .repositories(query)
+            .per_page(per.min(100))
+            .send()
+            .await?
+            .take_items()
+            .into_iter()
+            .map(RepoSummary::from_github)
+            .collect())
+    }
+}
+
+impl RepoSummary {
+    fn from_github(repo: Repository) -> Self {
+        Self {
+            owner: repo.owner.login,
+            repo: repo.name,
+            description: repo.description,
+            stars: repo.stargazers_count.unwrap_or(0),
+        }
+    }
+}
+
+impl From<gh::Asset> for Asset {
+    fn from(ass: gh::Asset) -> Self {
+        Self {
+            id: ass.id.0,
+            name: ass.name,
+            label: ass.label,
+            content_type: Some(ass.content_type),
+            size: u64::try_from(ass.size).ok(),
+            download_count: u64::try_from(ass.download_count).ok(),
+            uploader: Some(ass.uploader.login),
+            created_at: Some(ass.created_at),
+            updated_at: Some(ass.updated_at),
+            browser_download_url: ass.browser_download_url,
+        }
+    }
+}
+
+impl From<gh::Release> for Release {
+    fn from(rel: gh::Release) -> Self {
+        Self {
+            tag_name: rel.tag_name,
+            name: rel.name,
+            body: rel.body,
+            draft: rel.draft,
+            prerelease: rel.prerelease,
+            published_at: Some(rel.published_at),
+            html_url: Some(rel.html_url),
+            assets: rel.assets.into_iter().map(Asset::from).collect(),
+        }
+    }
+}
+
+/// Turn GitHub's 404 into `None`
+fn not_found<T>(res: Result<T, octocrab::Error>) -> Result<Option<T>, Error> {
+    match res {
+        Ok(x) => Ok(Some(x)),
+        Err(octocrab::Error::GitHub { source, .. }) if source.message == "Not Found" => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[async_trait(?Send)]
+impl ReleaseSource for GitHub {
+    async fn list_releases_page(&self, page: u32, per: u8) -> Result<Vec<Release>, Error> {
+        let inst = &self.client;
+        let repos = inst.repos(&self.owner, &self.repo);
+        let rels = repos.releases();
+        Ok(rels
+            .list()
+            .per_page(per.min(100))
+            .page(page)
+            .send()
+            .await?
+            .take_items()
+            .into_iter()
+            .map(Release::from)
+            .collect())
+    }
+    async fn list_releases(&self) -> Result<Vec<Release>, Error> {
+        // Follow the Link headers rather than counting pages
+        let inst = &self.client;
+        let repos = inst.repos(&self.owner, &self.repo);
+        let rels = repos.releases();
+        let mut current_page = rels.list().per_page(100).page(1u32).send().await?;
+        let mut prs = current_page.take_items();
+        while let Ok(Some(mut new_page)) = inst.get_page(&current_page.next).await {
+            prs.extend(new_page.take_items());
+            current_page = new_page;
+        }
+        Ok(prs.into_iter().map(Release::from).collect())
+    }
+    async fn get_latest(&self) -> Result<Option<Release>, Error> {
+        let inst = &self.client;
+        let repos = inst.repos(&self.owner, &self.repo);
+        let rels = repos.releases();
+        Ok(not_found(rels.get_latest().await)?.map(Release::from))
+    }
+    async fn get_by_tag(&self, tag: &str) -> Result<Option<Release>, Error> {
+        let inst = &self.client;
+        let repos = inst.repos(&self.owner, &self.repo);
+        let rels = repos.releases();
+        Ok(not_found(rels.get_by_tag(tag).await)?.map(Release::from))
+    }
+    async fn get_asset(&self, id: super::AssetId) -> Result<Option<Asset>, Error> {
+        let inst = &self.client;
+        let repos = inst.repos(&self.owner, &self.repo);
+        let rels = repos.releases();
+        Ok(not_found(rels.get_asset(AssetId(id)).await)?.map(Asset::from))
+    }
+    async fn download_asset(&self, asset: &Asset, progress: Progress) -> Result<Vec<u8>, Error> {
+        let (api, token) = match &self.enterprise {
+            Some(enterprise) => enterprise.clone(),
+            None => Self::github_com(),
+        };
+        let token = match token {
+            Some(token) => token,
+            None => return common::download_asset(asset.clone(), progress).await,
+        };
+        // Assets of private repositories are only served through the API, it redirects to the storage
+        let route = format!(
+            "repos/{}/{}/releases/assets/{}",
+            self.owner, self.repo, asset.id
+        );
+        let headers = vec![
+            String::from("Accept: application/octet-stream"),
+            format!("Authorization: token {}", token),
+        ];
+        common::download(api.join(&route)?, headers, progress).await
+    }
+}
@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use url::Url;
+
+use super::{get_json, Asset, AssetId, Error, Release, ReleaseSource};
+use crate::common::{self, Progress};
+
+/// GitLab's v4 releases API, gitlab.com or a self-hosted instance.
+/// `GITLAB_TOKEN` is sent if set, with API requests and downloads from the instance, for private projects
+#[derive(Debug, Clone)]
+pub struct GitLab {
+    client: reqwest::Client,
+    /// `.../api/v4/projects/GROUP%2FPROJECT`
+    project_url: Url,
+    token: Option<String>,
+}
+
+impl GitLab {
+    /// `token` overrides the environment variable
+    pub fn new(host: Option<Url>, token: Option<String>, owner: &str, repo: &str) -> Self {
+        let mut project_url =
+            host.unwrap_or_else(|| Url::parse("https://gitlab.com").expect("valid url"));
+        project_url
+            .path_segments_mut()
+            .expect("http url")
+            .pop_if_empty()
+            .extend(&["api", "v4", "projects", &format!("{}/{}", owner, repo)]);
+        Self {
+            client: reqwest::Client::new(),
+            project_url,
+            token: token.or_else(|| std::env::var("GITLAB_TOKEN").ok()),
+        }
+    }
+
+    fn get(&self, path: &[&str]) -> reqwest::RequestBuilder {
+        let mut url = self.project_url.clone();
+        url.path_segments_mut()
+            .expect("http url")
+            .push("releases")
+            .extend(path);
+        let req = self.client.get(url);
+        match &self.token {
+            Some(token) => req.header("PRIVATE-TOKEN", token),
+            None => req,
+        }
+    }
+
+    /// The API route for a link to one of the project's own release files,
+    /// the web route `/-/releases/TAG/downloads/PATH` does not take a token
+    fn api_download_url(&self, url: &Url) -> Option<Url> {
+        let (_, rest) = url.path().split_once("/-/releases/")?;
+        let (tag, file) = rest.split_once("/downloads/")?;
+        let api = format!("{}/releases/{}/downloads/{}", self.project_url, tag, file);
+        Url::parse(&api).ok()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GlRelease {
+    tag_name: String,
+    name: Option<String>,
+    description: Option<String>,
+    released_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    upcoming_release: bool,
+    #[serde(rename = "_links")]
+    links: Option<GlReleaseLinks>,
+    assets: GlAssets,
+}
+
+#[derive(Debug, Deserialize)]
+struct GlReleaseLinks {
+    #[serde(rename = "self")]
+    this: Option<Url>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GlAssets {
+    #[serde(default)]
+    links: Vec<GlLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GlLink {
+    id: u64,
+    name: String,
+    url: Url,
+    direct_asset_url: Option<Url>,
+}
+
+impl From<GlRelease> for Release {
+    fn from(rel: GlRelease) -> Self {
+        Self {
+            tag_name: rel.tag_name,
+            name: rel.name,
+            body: rel.description,
+            draft: false,
+            prerelease: rel.upcoming_release,
+            published_at: rel.released_at,
+            html_url: rel.links.and_then(|l| l.this),
+            assets: rel
+                .assets
+                .links
+                .into_iter()
+                .map(|link| Asset {
+                    id: link.id,
+                    name: link.name,
+                    label: None,
+                    content_type: None,
+                    size: None,
+                    download_count: None,
+                    uploader: None,
+                    created_at: None,
+                    updated_at: None,
+                    browser_download_url: link.direct_asset_url.unwrap_or(link.url),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl ReleaseSource for GitLab {
+    async fn list_releases_page(&self, page: u32, per: u8) -> Result<Vec<Release>, Error> {
+        let req = self
+            .get(&[])
+            .query(&[("page", page.to_string()), ("per_page", per.to_string())]);
+        let rels: Option<Vec<GlRelease>> = get_json(req).await?;
+        Ok(rels
+            .unwrap_or_default()
+            .into_iter()
+            .map(Release::from)
+            .collect())
+    }
+    async fn get_latest(&self) -> Result<Option<Release>, Error> {
+        // Releases are sorted by `released_at`, newest first
+        Ok(self.list_releases_page(1, 1).await?.into_iter().next())
+    }
+    async fn get_by_tag(&self, tag: &str) -> Result<Option<Release>, Error> {
+        let rel: Option<GlRelease> = get_json(self.get(&[tag])).await?;
+        Ok(rel.map(Release::from))
+    }
+    async fn get_asset(&self, id: AssetId) -> Result<Option<Asset>, Error> {
+        // Release links are only looked up by id within their release
+        super::find_asset_by_id(self, id).await
+    }
+    async fn download_asset(&self, asset: &Asset, progress: Progress) -> Result<Vec<u8>, Error> {
+        let url = &asset.browser_download_url;
+        match &self.token {
+            // Links to other hosts don't get the token
+            Some(token) if url.origin() == self.project_url.origin() => {
+                let url = self.api_download_url(url).unwrap_or_else(|| url.clone());
+                let headers = vec![format!("PRIVATE-TOKEN: {}", token)];
+                common::download(url, headers, progress).await
+            }
+            _ => common::download_asset(asset.clone(), progress).await,
+        }
+    }
+}
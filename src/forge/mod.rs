@@ -0,0 +1,417 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use percent_encoding::percent_decode_str;
+use serde::de::DeserializeOwned;
+use std::{fmt, str::FromStr};
+use url::{Position, Url};
+
+use crate::{
+    common,
+    config::{Config, HostConfig},
+};
+
+mod gitea;
+mod github;
+mod gitlab;
+
+pub use gitea::Gitea;
+pub use github::GitHub;
+pub use gitlab::GitLab;
+
+pub type Error = Box<dyn std::error::Error>;
+pub type AssetId = u64;
+
+/// A release, independent of the forge it was found on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub body: Option<String>,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub published_at: Option<DateTime<Utc>>,
+    pub html_url: Option<Url>,
+    pub assets: Vec<Asset>,
+}
+
+/// A downloadable file attached to a [`Release`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asset {
+    pub id: AssetId,
+    pub name: String,
+    pub label: Option<String>,
+    pub content_type: Option<String>,
+    pub size: Option<u64>,
+    pub download_count: Option<u64>,
+    pub uploader: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub browser_download_url: Url,
+}
+
+/// A repository found by [`GitHub::search`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoSummary {
+    pub owner: String,
+    pub repo: String,
+    pub description: Option<String>,
+    pub stars: u32,
+}
+
+/// Everything grpm needs from a forge's releases API
+#[async_trait(?Send)]
+pub trait ReleaseSource {
+    /// One page of releases, newest first. Pages start at 1
+    async fn list_releases_page(&self, page: u32, per: u8) -> Result<Vec<Release>, Error>;
+    async fn get_latest(&self) -> Result<Option<Release>, Error>;
+    async fn get_by_tag(&self, tag: &str) -> Result<Option<Release>, Error>;
+
+    async fn list_releases(&self) -> Result<Vec<Release>, Error> {
+        let mut all = Vec::new();
+        for page in 1.. {
+            // Some forges cap the page size, so only an empty page marks the end
+            let rels = self.list_releases_page(page, 100).await?;
+            if rels.is_empty() {
+                break;
+            }
+            all.extend(rels);
+        }
+        Ok(all)
+    }
+    async fn get_asset(&self, id: AssetId) -> Result<Option<Asset>, Error>;
+    async fn download_asset(
+        &self,
+        asset: &Asset,
+        progress: common::Progress,
+    ) -> Result<Vec<u8>, Error> {
+        common::download_asset(asset.clone(), progress).await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+impl ForgeKind {
+    fn prefix(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "github",
+            ForgeKind::GitLab => "gitlab",
+            ForgeKind::Gitea => "gitea",
+        }
+    }
+    fn public_host(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "github.com",
+            ForgeKind::GitLab => "gitlab.com",
+            ForgeKind::Gitea => "codeberg.org",
+        }
+    }
+    /// Guess the forge from a well known host name
+    fn from_host(host: &str) -> Option<Self> {
+        match host {
+            "github.com" => Some(ForgeKind::GitHub),
+            "gitlab.com" => Some(ForgeKind::GitLab),
+            "codeberg.org" => Some(ForgeKind::Gitea),
+            h if h.starts_with("gitlab.") => Some(ForgeKind::GitLab),
+            h if h.starts_with("gitea.") || h.starts_with("forgejo.") => Some(ForgeKind::Gitea),
+            _ => None,
+        }
+    }
+}
+
+/// Where to find a repository's releases
+///
+/// - `OWNER/REPO` or `github:OWNER/REPO`
+/// - `gitlab:GROUP/PROJECT`, subgroups are allowed
+/// - `codeberg:OWNER/REPO`
+/// - a repository URL or `HOST/OWNER/REPO`, the forge is guessed from the host
+///   and unknown hosts are taken to be GitHub Enterprise
+/// - `gitlab:URL`, `gitea:URL` or `forgejo:URL` for self-hosted instances on other hosts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoSpec {
+    pub kind: ForgeKind,
+    /// Web root of a self-hosted instance, `None` for the forge's public instance
+    pub host: Option<Url>,
+    /// Everything before the last path segment (GitLab groups may be nested)
+    pub owner: String,
+    pub repo: String,
+}
+
+impl RepoSpec {
+    /// Build the release source, using the `[hosts]` section of the global config for self-hosted forges
+    pub fn source(&self) -> Result<Box<dyn ReleaseSource>, Error> {
+        let host = match &self.host {
+            Some(host) => host,
+            None => {
+                return Ok(match self.kind {
+                    ForgeKind::GitHub => Box::new(GitHub::new(&self.owner, &self.repo)),
+                    ForgeKind::GitLab => Box::new(GitLab::new(None, None, &self.owner, &self.repo)),
+                    ForgeKind::Gitea => Box::new(Gitea::new(None, None, &self.owner, &self.repo)),
+                })
+            }
+        };
+        let config = Config::load_global()?;
+        let conf = host.host_str().and_then(|h| config.hosts.get(h));
+        let token = conf.and_then(HostConfig::token);
+        Ok(match self.kind {
+            ForgeKind::GitHub => Box::new(GitHub::enterprise(host, conf, &self.owner, &self.repo)?),
+            ForgeKind::GitLab => Box::new(GitLab::new(
+                Some(host.clone()),
+                token,
+                &self.owner,
+                &self.repo,
+            )),
+            ForgeKind::Gitea => Box::new(Gitea::new(
+                Some(host.clone()),
+                token,
+                &self.owner,
+                &self.repo,
+            )),
+        })
+    }
+
+    /// `HOST/OWNER/REPO`, identifies the repository across forges for caches and saved state
+    pub fn key(&self) -> String {
+        format!("{}/{}/{}", self.host_port(), self.owner, self.repo)
+    }
+
+    /// The name to install a package from this repository under, `REPO` for github.com and
+    /// `REPO@HOST` elsewhere so that repositories of the same name get a store directory each
+    pub fn package_name(&self) -> String {
+        match (&self.host, self.kind) {
+            (None, ForgeKind::GitHub) => self.repo.clone(),
+            _ => format!("{}@{}", self.repo, self.host_port()),
+        }
+    }
+
+    fn host_port(&self) -> &str {
+        match &self.host {
+            Some(host) => &host[Position::BeforeHost..Position::AfterPort],
+            None => self.kind.public_host(),
+        }
+    }
+
+    /// The forge's web page comparing two tags
+    pub fn compare_url(&self, from: &str, to: &str) -> Result<Url, Error> {
+        let root = match &self.host {
+            Some(host) => host.clone(),
+            None => Url::parse(&format!("https://{}/", self.kind.public_host()))?,
+        };
+        let compare = match self.kind {
+            ForgeKind::GitLab => "-/compare",
+            ForgeKind::GitHub | ForgeKind::Gitea => "compare",
+        };
+        let mut url = root;
+        url.path_segments_mut()
+            .map_err(|_| "Not a web root")?
+            .pop_if_empty()
+            .extend(self.owner.split('/'))
+            .push(&self.repo)
+            .extend(compare.split('/'))
+            .push(&format!("{}...{}", from, to));
+        Ok(url)
+    }
+}
+
+/// A repository link, possibly pointing at one release or asset, eg.
+/// `https://github.com/OWNER/REPO/releases/tag/TAG` or `.../releases/download/TAG/ASSET`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoLink {
+    pub repo: RepoSpec,
+    pub tag: Option<String>,
+    pub asset: Option<String>,
+}
+
+impl RepoLink {
+    /// The `[RELEASE]` selector for the linked tag
+    pub fn release_selector(&self) -> Option<String> {
+        self.tag.as_ref().map(|tag| format!("t:{}", tag))
+    }
+    /// A regex matching exactly the linked asset
+    pub fn asset_regex(&self) -> Option<String> {
+        self.asset
+            .as_ref()
+            .map(|asset| format!("^{}$", regex::escape(asset)))
+    }
+
+    fn from_path(kind: ForgeKind, host: Option<Url>, path: &str) -> Result<Self, String> {
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        let segs = path.split('/').collect::<Vec<_>>();
+
+        // GitLab groups nest, so there the repository ends where its `-` pages start
+        let end = match kind {
+            ForgeKind::GitLab => (2..segs.len())
+                .find(|&i| segs[i] == "-" || segs[i] == "releases")
+                .unwrap_or(segs.len()),
+            _ => segs.len().min(2),
+        };
+        let (repo, rest) = segs.split_at(end);
+        let rest = rest.strip_prefix(&["-"][..]).unwrap_or(rest);
+        let (tag, asset) = match rest {
+            ["releases", "tag", tag, ..] => (Some(*tag), None),
+            ["releases", "download", tag, asset, ..] => (Some(*tag), Some(*asset)),
+            ["releases", "latest", ..] => (None, None),
+            // GitLab's `/-/releases/TAG`
+            ["releases", tag, ..] if kind == ForgeKind::GitLab => (Some(*tag), None),
+            _ => (None, None),
+        };
+
+        match repo.split_last() {
+            Some((name, owner)) if !owner.is_empty() && !name.is_empty() => Ok(Self {
+                repo: RepoSpec {
+                    kind,
+                    host,
+                    owner: owner.join("/"),
+                    repo: (*name).to_owned(),
+                },
+                // Links escape eg. the `+` of `v1.0+build` and spaces in asset names
+                tag: tag.map(decode),
+                asset: asset.map(decode),
+            }),
+            _ => Err(format!("Expected OWNER/REPO, got `{}`", path)),
+        }
+    }
+    fn from_url(kind: Option<ForgeKind>, url: &str) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|e| format!("{}: {}", url, e))?;
+        let host = url.host_str().ok_or_else(|| format!("{}: no host", url))?;
+        let kind = kind
+            .or_else(|| ForgeKind::from_host(host))
+            .unwrap_or(ForgeKind::GitHub);
+        // The public instances are left implicit so that they display as a prefix
+        let host_url = if kind.public_host() == host {
+            None
+        } else {
+            let mut root = url.clone();
+            root.set_path("");
+            root.set_query(None);
+            root.set_fragment(None);
+            Some(root)
+        };
+        Self::from_path(kind, host_url, url.path())
+    }
+}
+
+/// A percent-decoded path segment
+fn decode(seg: &str) -> String {
+    percent_decode_str(seg).decode_utf8_lossy().into_owned()
+}
+
+impl FromStr for RepoLink {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("https://") || s.starts_with("http://") {
+            return Self::from_url(None, s);
+        }
+        let (kind, rest) = match s.split_once(':') {
+            Some(("github", rest)) => (ForgeKind::GitHub, rest),
+            Some(("gitlab", rest)) => (ForgeKind::GitLab, rest),
+            Some(("codeberg", rest)) => (ForgeKind::Gitea, rest),
+            Some(("gitea", rest)) | Some(("forgejo", rest)) => (ForgeKind::Gitea, rest),
+            _ => (ForgeKind::GitHub, s),
+        };
+        if rest.starts_with("https://") || rest.starts_with("http://") {
+            return Self::from_url(Some(kind), rest);
+        }
+        // GitHub owner names can't contain a dot, so without a prefix this is `HOST/OWNER/REPO`.
+        // GitLab groups and Gitea organisations can, a prefixed self-hosted instance needs a URL
+        let segs = rest.split('/').collect::<Vec<_>>();
+        if rest.len() == s.len() && segs.len() >= 3 && segs[0].contains('.') {
+            return Self::from_url(None, &format!("https://{}", rest));
+        }
+        if kind == ForgeKind::Gitea && !s.starts_with("codeberg:") {
+            return Err(format!(
+                "{}: self-hosted Gitea needs a URL, eg. gitea:https://HOST/OWNER/REPO",
+                s
+            ));
+        }
+        Self::from_path(kind, None, rest)
+    }
+}
+
+impl FromStr for RepoSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<RepoLink>().map(|link| link.repo)
+    }
+}
+
+impl fmt::Display for RepoSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.host, self.kind) {
+            (None, ForgeKind::GitHub) => write!(f, "{}/{}", self.owner, self.repo),
+            (None, ForgeKind::Gitea) => write!(f, "codeberg:{}/{}", self.owner, self.repo),
+            (None, kind) => write!(f, "{}:{}/{}", kind.prefix(), self.owner, self.repo),
+            // `HOST/OWNER/REPO` reads back as https on the default port
+            (Some(host), ForgeKind::GitHub)
+                if host.scheme() == "https" && host.port().is_none() =>
+            {
+                write!(
+                    f,
+                    "{}/{}/{}",
+                    host.host_str().unwrap_or_default(),
+                    self.owner,
+                    self.repo
+                )
+            }
+            (Some(host), ForgeKind::GitHub) => write!(
+                f,
+                "{}/{}/{}",
+                host.as_str().trim_end_matches('/'),
+                self.owner,
+                self.repo
+            ),
+            (Some(host), kind) => write!(
+                f,
+                "{}:{}/{}/{}",
+                kind.prefix(),
+                host.as_str().trim_end_matches('/'),
+                self.owner,
+                self.repo
+            ),
+        }
+    }
+}
+
+/// The release source for an `OWNER` / `REPO` pair as typed on the command line or in the TUI.
+/// Forge prefixes and URLs survive being split on the first `/`, so they are joined back up
+pub fn source(owner: &str, repo: &str) -> Result<Box<dyn ReleaseSource>, Error> {
+    format!("{}/{}", owner, repo).parse::<RepoSpec>()?.source()
+}
+
+/// Look an asset up by going through the releases a page at a time, for forges that only
+/// find assets within a release
+pub async fn find_asset_by_id(
+    src: &dyn ReleaseSource,
+    id: AssetId,
+) -> Result<Option<Asset>, Error> {
+    for page in 1.. {
+        let rels = src.list_releases_page(page, 100).await?;
+        if rels.is_empty() {
+            break;
+        }
+        let mut assets = rels.into_iter().flat_map(|rel| rel.assets);
+        if let Some(ass) = assets.find(|ass| ass.id == id) {
+            return Ok(Some(ass));
+        }
+    }
+    Ok(None)
+}
+
+/// GET a JSON document, `None` on 404
+async fn get_json<T: DeserializeOwned>(req: reqwest::RequestBuilder) -> Result<Option<T>, Error> {
+    let resp = req.header("User-Agent", "grpm").send().await?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(resp.error_for_status()?.json().await?))
+}
+
+#[cfg(test)]
+mod tests;
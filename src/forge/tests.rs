@@ -0,0 +1,89 @@
+//! Parsing repository specs and links, and reading back what they display as
+use super::*;
+
+fn spec(s: &str) -> RepoSpec {
+    s.parse().unwrap_or_else(|e| panic!("{}: {}", s, e))
+}
+
+#[test]
+fn specs_read_back_from_their_display() {
+    for s in &[
+        "owner/repo",
+        "gitlab:group/sub/project",
+        "codeberg:owner/repo",
+        "ghe.example.com/owner/repo",
+        "http://ghe.local/owner/repo",
+        "https://ghe.local:8443/owner/repo",
+        "http://127.0.0.1:8080/owner/repo",
+        "gitlab:https://git.example.com/group/project",
+        "gitea:http://git.local:3000/owner/repo",
+    ] {
+        let parsed = spec(s);
+        assert_eq!(
+            spec(&parsed.to_string()),
+            parsed,
+            "{} displays as {}",
+            s,
+            parsed
+        );
+    }
+}
+
+#[test]
+fn a_github_enterprise_host_keeps_its_scheme_and_port() {
+    let parsed = spec("http://ghe.local:8080/org/repo");
+    assert_eq!(parsed.kind, ForgeKind::GitHub);
+    assert_eq!(parsed.to_string(), "http://ghe.local:8080/org/repo");
+    assert_eq!(parsed.key(), "ghe.local:8080/org/repo");
+    assert_eq!(
+        spec("https://ghe.local/org/repo").to_string(),
+        "ghe.local/org/repo"
+    );
+}
+
+#[test]
+fn packages_from_other_hosts_are_named_after_the_host_too() {
+    assert_eq!(spec("a/tool").package_name(), "tool");
+    assert_eq!(
+        spec("ghe.example/b/tool").package_name(),
+        "tool@ghe.example"
+    );
+    assert_eq!(
+        spec("http://ghe.local:8080/b/tool").package_name(),
+        "tool@ghe.local:8080"
+    );
+    assert_eq!(spec("gitlab:g/tool").package_name(), "tool@gitlab.com");
+}
+
+#[test]
+fn only_an_unprefixed_dotted_first_segment_is_a_host() {
+    let parsed = spec("ghe.example.com/owner/repo");
+    assert_eq!(parsed.host.unwrap().as_str(), "https://ghe.example.com/");
+    assert_eq!(
+        (parsed.owner.as_str(), parsed.repo.as_str()),
+        ("owner", "repo")
+    );
+
+    let parsed = spec("gitlab:my.group/project");
+    assert_eq!((parsed.kind, parsed.host), (ForgeKind::GitLab, None));
+    assert_eq!(parsed.owner, "my.group");
+    let parsed = spec("codeberg:my.org/repo");
+    assert_eq!((parsed.kind, parsed.host), (ForgeKind::Gitea, None));
+    assert_eq!(parsed.owner, "my.org");
+}
+
+#[test]
+fn release_and_asset_links_are_percent_decoded() {
+    let link: RepoLink = "https://github.com/owner/repo/releases/tag/v1.0%2Bbuild"
+        .parse()
+        .unwrap();
+    assert_eq!(link.release_selector().as_deref(), Some("t:v1.0+build"));
+
+    let link: RepoLink = "https://github.com/owner/repo/releases/download/v1.0/tool%20linux.tar.gz"
+        .parse()
+        .unwrap();
+    assert_eq!(link.tag.as_deref(), Some("v1.0"));
+    assert_eq!(link.asset.as_deref(), Some("tool linux.tar.gz"));
+    let re = regex::Regex::new(&link.asset_regex().unwrap()).unwrap();
+    assert!(re.is_match("tool linux.tar.gz"));
+}
@@ -0,0 +1,259 @@
+//! Key bindings of the TUI, a preset picked by `[tui] keymap` with `[tui.keys]` on top
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+
+use crate::config::TuiConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    Quit,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Next,
+    Prev,
+    Select,
+    Back,
+    Insert,
+    Install,
+    Download,
+    Save,
+    CopyLink,
+    ToggleLog,
+    ToggleFuzzy,
+    ScrollDown,
+    ScrollUp,
+    Fullscreen,
+    SearchTab,
+    InstalledTab,
+    StarredTab,
+    Cancel,
+    Upgrade,
+    Rollback,
+    Uninstall,
+    Open,
+    Help,
+    Command,
+    Favourite,
+    Changelog,
+}
+
+/// Name in `[tui.keys]`, what it does, then the keys of the default and the vim preset
+#[rustfmt::skip]
+const ACTIONS: [(Action, &str, &str, &str, &str); 35] = [
+    (Action::Quit, "quit", "Quit", "q ctrl-c", "q ctrl-c"),
+    (Action::Up, "up", "Move up", "up k", "up k"),
+    (Action::Down, "down", "Move down", "down j", "down j"),
+    (Action::Left, "left", "Move left", "left h", "left h"),
+    (Action::Right, "right", "Move right", "right l", "right l"),
+    (Action::PageUp, "page-up", "Page up", "pageup", "pageup ctrl-u ctrl-b"),
+    (Action::PageDown, "page-down", "Page down", "pagedown space", "pagedown space ctrl-d ctrl-f"),
+    (Action::Top, "top", "First row", "home g", "home g"),
+    (Action::Bottom, "bottom", "Last row", "end G", "end G"),
+    (Action::Next, "next", "Next pane", "tab", "tab"),
+    (Action::Prev, "prev", "Previous pane", "backtab", "backtab"),
+    (Action::Select, "select", "Press the button, open the assets", "enter space", "enter space"),
+    (Action::Back, "back", "Back to the fields, close", "esc", "esc"),
+    (Action::Insert, "insert", "Type into the field", "enter", "i a enter"),
+    (Action::Install, "install", "Install the selected asset", "i", "i"),
+    (Action::Download, "download", "Download the selected asset", "d", "d"),
+    (Action::Save, "save", "Save the fields to the package file", "s", "s"),
+    (Action::CopyLink, "copy-link", "Copy the asset link", "y", "y"),
+    (Action::ToggleLog, "toggle-log", "Show the log", "L", "L"),
+    (Action::ToggleFuzzy, "toggle-fuzzy", "Fuzzy or regex filters", "f F2", "f F2"),
+    (Action::ScrollDown, "scroll-down", "Scroll the description down", "J", "J ctrl-e"),
+    (Action::ScrollUp, "scroll-up", "Scroll the description up", "K", "K ctrl-y"),
+    (Action::Fullscreen, "fullscreen", "Full screen description", "z", "z"),
+    (Action::SearchTab, "search-tab", "Search tab", "1", "1"),
+    (Action::InstalledTab, "installed-tab", "Installed tab", "2", "2"),
+    (Action::StarredTab, "starred-tab", "Starred tab", "3", "3"),
+    (Action::Cancel, "cancel", "Cancel the selected job", "x c delete", "x c delete"),
+    (Action::Upgrade, "upgrade", "Upgrade the package", "u", "u"),
+    (Action::Rollback, "rollback", "Roll back to the previous version", "r", "r"),
+    (Action::Uninstall, "uninstall", "Uninstall the package", "x delete", "x delete"),
+    (Action::Open, "open", "Open the package in the search tab", "enter o", "enter o"),
+    (Action::Help, "help", "Show this help", "?", "?"),
+    (Action::Command, "command", "Run a command, see below", ":", ":"),
+    (Action::Favourite, "favourite", "Add the repository to the favourites or remove it", "*", "*"),
+    (Action::Changelog, "changelog", "Release notes since the installed version", "C", "C"),
+];
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    keys: BTreeMap<Action, Vec<KeyEvent>>,
+    /// Focusing a text field starts typing into it, otherwise [`Action::Insert`] does
+    pub insert_on_focus: bool,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::preset("default").unwrap()
+    }
+}
+
+impl Keymap {
+    pub fn preset(name: &str) -> Option<Self> {
+        let vim = match name {
+            "default" => false,
+            "vim" => true,
+            _ => return None,
+        };
+        let keys = ACTIONS
+            .iter()
+            .map(|&(action, _, _, default, vim_keys)| {
+                let keys = if vim { vim_keys } else { default };
+                (action, parse_keys(keys).unwrap())
+            })
+            .collect();
+        Some(Self {
+            keys,
+            insert_on_focus: !vim,
+        })
+    }
+
+    pub fn from_config(config: &TuiConfig) -> Result<Self, String> {
+        let preset = config.keymap.as_deref().unwrap_or("default");
+        let mut keymap =
+            Self::preset(preset).ok_or_else(|| format!("Unknown keymap {}", preset))?;
+        for (name, keys) in &config.keys {
+            let action = ACTIONS
+                .iter()
+                .find(|(_, n, ..)| n == name)
+                .map(|&(action, ..)| action)
+                .ok_or_else(|| format!("Unknown action {} in [tui.keys]", name))?;
+            keymap.keys.insert(action, parse_keys(keys)?);
+        }
+        Ok(keymap)
+    }
+
+    /// The first of `actions` that `key` is bound to
+    pub fn action(&self, key: KeyEvent, actions: &[Action]) -> Option<Action> {
+        let key = normalize(key);
+        actions
+            .iter()
+            .copied()
+            .find(|action| self.keys.get(action).is_some_and(|k| k.contains(&key)))
+    }
+    pub fn is(&self, key: KeyEvent, action: Action) -> bool {
+        self.action(key, &[action]).is_some()
+    }
+
+    /// Every bound action as its keys, what it does and its name in `[tui.keys]`
+    pub fn bindings(&self) -> Vec<(String, &'static str, &'static str)> {
+        ACTIONS
+            .iter()
+            .filter(|(action, ..)| self.keys.get(action).is_some_and(|k| !k.is_empty()))
+            .map(|&(action, name, help, ..)| (self.keys(action), help, name))
+            .collect()
+    }
+
+    /// The keys of `action` for labels, eg. `q/ctrl-c`
+    pub fn keys(&self, action: Action) -> String {
+        self.keys
+            .get(&action)
+            .map(|keys| {
+                keys.iter()
+                    .map(|&k| key_name(k))
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Whether `key` types a character into a text field
+pub fn is_text(key: KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char(_))
+        && !key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+}
+
+/// Terminals add Shift to upper case letters and Shift-Tab, the key already says so.
+/// A held down key repeats the same action
+fn normalize(key: KeyEvent) -> KeyEvent {
+    let modifiers = match key.code {
+        KeyCode::Char(_) | KeyCode::BackTab => key.modifiers - KeyModifiers::SHIFT,
+        _ => key.modifiers,
+    };
+    KeyEvent::new(key.code, modifiers)
+}
+
+fn parse_keys(keys: &str) -> Result<Vec<KeyEvent>, String> {
+    keys.split_whitespace().map(parse_key).collect()
+}
+
+/// `q`, `G`, `ctrl-c`, `alt-enter`, `F2`, `pagedown` and the like
+pub fn parse_key(key: &str) -> Result<KeyEvent, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = key;
+    // A lone `-` is a key, not a separator
+    while let Some((modifier, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" | "c" => KeyModifiers::CONTROL,
+            "alt" | "a" | "m" => KeyModifiers::ALT,
+            "shift" | "s" => KeyModifiers::SHIFT,
+            _ => return Err(format!("Unknown modifier in {}", key)),
+        };
+        name = rest;
+    }
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match name.to_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            f => match f.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                Some(n) => KeyCode::F(n),
+                None => return Err(format!("Unknown key {}", key)),
+            },
+        },
+    };
+    Ok(normalize(KeyEvent::new(code, modifiers)))
+}
+
+fn key_name(key: KeyEvent) -> String {
+    let mut name = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        name.push_str("ctrl-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        name.push_str("alt-");
+    }
+    if key.modifiers.contains(KeyModifiers::SHIFT) {
+        name.push_str("shift-");
+    }
+    match key.code {
+        KeyCode::Char(' ') => name.push_str("space"),
+        KeyCode::Char(c) => name.push(c),
+        KeyCode::F(n) => name.push_str(&format!("F{}", n)),
+        KeyCode::BackTab => name.push_str("shift-tab"),
+        KeyCode::PageUp => name.push_str("pgup"),
+        KeyCode::PageDown => name.push_str("pgdn"),
+        KeyCode::Up => name.push('↑'),
+        KeyCode::Down => name.push('↓'),
+        KeyCode::Left => name.push('←'),
+        KeyCode::Right => name.push('→'),
+        code => name.push_str(&format!("{:?}", code).to_lowercase()),
+    }
+    name
+}
@@ -0,0 +1,193 @@
+//! Single line text fields with a cursor, readline style keys and a history of what was entered
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{mem, ops::Range};
+
+/// Entries kept in the history of a field
+const HISTORY: usize = 50;
+
+/// Keys of a field while typing into it, for the help overlay
+pub const KEYS: [(&str, &str); 10] = [
+    ("←/→", "Move the cursor, past either end to the next field"),
+    ("ctrl-←/→ alt-b/f", "Move by words"),
+    ("home/ctrl-a", "Start of the field"),
+    ("end/ctrl-e", "End of the field"),
+    ("backspace/delete", "Delete a character"),
+    ("ctrl-w alt-backspace", "Delete the word before the cursor"),
+    ("alt-d", "Delete the word after the cursor"),
+    ("ctrl-u/ctrl-k", "Delete up to the start or end"),
+    ("ctrl-p/ctrl-n", "Recall what was entered before"),
+    (
+        "paste",
+        "Inserted as one line, its newlines don't press Enter",
+    ),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct LineEdit {
+    text: String,
+    /// Byte offset into `text`, always on a character boundary
+    cursor: usize,
+    /// Oldest first, see `commit`
+    history: Vec<String>,
+    /// Entry of `history` being shown, `None` while editing a line of its own
+    recalled: Option<usize>,
+    /// The line of its own, put back when going past the newest entry
+    draft: String,
+}
+
+impl LineEdit {
+    pub fn new(text: impl Into<String>) -> Self {
+        let mut edit = Self::default();
+        edit.set(text);
+        edit
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, the cursor goes to its end
+    pub fn set(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.text.len();
+        self.recalled = None;
+    }
+
+    /// Insert at the cursor, without line breaks and other control characters
+    pub fn insert_str(&mut self, s: &str) {
+        let s = s.chars().filter(|c| !c.is_control()).collect::<String>();
+        self.replace(self.cursor..self.cursor, &s);
+    }
+
+    /// What was entered before, oldest first
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+    pub fn set_history(&mut self, mut history: Vec<String>) {
+        let len = history.len();
+        history.drain(..len.saturating_sub(HISTORY));
+        self.history = history;
+        self.recalled = None;
+    }
+
+    /// Remember the text as the newest entry of the history
+    pub fn commit(&mut self) {
+        self.recalled = None;
+        if self.text.is_empty() {
+            return;
+        }
+        let text = &self.text;
+        self.history.retain(|h| h != text);
+        self.history.push(text.clone());
+        if self.history.len() > HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    /// Edit or move the cursor, returns whether the key was used.
+    /// Left and Right are not used at the start and end, so the caller can move to the next field
+    pub fn on_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let end = self.text.len();
+        match key.code {
+            KeyCode::Char(c) if !ctrl && !alt => {
+                self.replace(self.cursor..self.cursor, &c.to_string())
+            }
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = end,
+            KeyCode::Char('b') if alt => self.cursor = self.word_start(),
+            KeyCode::Char('f') if alt => self.cursor = self.word_end(),
+            KeyCode::Char('w') if ctrl => self.replace(self.word_start()..self.cursor, ""),
+            KeyCode::Char('d') if alt => self.replace(self.cursor..self.word_end(), ""),
+            KeyCode::Char('u') if ctrl => self.replace(0..self.cursor, ""),
+            KeyCode::Char('k') if ctrl => self.replace(self.cursor..end, ""),
+            KeyCode::Char('p') if ctrl => return self.recall(true),
+            KeyCode::Char('n') if ctrl => return self.recall(false),
+            KeyCode::Backspace if ctrl || alt => self.replace(self.word_start()..self.cursor, ""),
+            KeyCode::Backspace => self.replace(self.prev()..self.cursor, ""),
+            KeyCode::Delete => self.replace(self.cursor..self.next(), ""),
+            KeyCode::Left if ctrl || alt => self.cursor = self.word_start(),
+            KeyCode::Right if ctrl || alt => self.cursor = self.word_end(),
+            KeyCode::Left if self.cursor > 0 => self.cursor = self.prev(),
+            KeyCode::Right if self.cursor < end => self.cursor = self.next(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = end,
+            _ => return false,
+        }
+        true
+    }
+
+    /// The end of the text that fits into `width` columns with the cursor, and the cursor's column in it
+    pub fn view(&self, width: u16) -> (&str, u16) {
+        let cursor = self.text[..self.cursor].chars().count();
+        let skip = (cursor + 1).saturating_sub(width.max(1) as usize);
+        let start = self
+            .text
+            .char_indices()
+            .nth(skip)
+            .map_or(self.text.len(), |(i, _)| i);
+        (&self.text[start..], (cursor - skip) as u16)
+    }
+
+    /// Every edit goes through here, it makes the text a line of its own rather than an entry of the history
+    fn replace(&mut self, range: Range<usize>, with: &str) {
+        self.cursor = range.start + with.len();
+        self.text.replace_range(range, with);
+        self.recalled = None;
+    }
+
+    fn prev(&self) -> usize {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+    fn next(&self) -> usize {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    /// Start of the word before the cursor, skipping anything between them
+    fn word_start(&self) -> usize {
+        self.text[..self.cursor]
+            .trim_end_matches(|c| !is_word(c))
+            .trim_end_matches(is_word)
+            .len()
+    }
+    /// End of the word after the cursor, skipping anything between them
+    fn word_end(&self) -> usize {
+        let rest = self.text[self.cursor..]
+            .trim_start_matches(|c| !is_word(c))
+            .trim_start_matches(is_word);
+        self.text.len() - rest.len()
+    }
+
+    /// Step through the history, returns whether there was anything to step to
+    fn recall(&mut self, older: bool) -> bool {
+        let len = self.history.len();
+        let recalled = match (self.recalled, older) {
+            (None, true) if len > 0 => Some(len - 1),
+            (Some(i), true) if i > 0 => Some(i - 1),
+            (Some(i), false) if i + 1 < len => Some(i + 1),
+            (Some(_), false) => None,
+            _ => return false,
+        };
+        if self.recalled.is_none() {
+            self.draft = mem::take(&mut self.text);
+        }
+        self.text = match recalled {
+            Some(i) => self.history[i].clone(),
+            None => mem::take(&mut self.draft),
+        };
+        self.cursor = self.text.len();
+        self.recalled = recalled;
+        true
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
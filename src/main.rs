@@ -1,8 +1,32 @@
-#![feature(maybe_uninit_array_assume_init)]
-#![feature(maybe_uninit_uninit_array)]
+mod commands;
 mod common;
+mod config;
+mod detect;
+mod forge;
+mod keymap;
+mod lineedit;
+mod markdown;
+#[cfg(test)]
+mod mockgithub;
+mod project;
+mod shim;
+mod state;
+mod store;
+mod theme;
 mod tuiapp;
 
+use common::AssetFinder;
+use config::Config;
+use forge::RepoLink;
+use project::Project;
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, Write},
+    os::unix::ffi::OsStrExt,
+};
+use tokio::runtime::Runtime;
+
 const HELP: &str = "\
 Github Releases Package Manager
 
@@ -10,6 +34,7 @@ Usage: grpm [OPTIONS] command
 
 OPTIONS:
     -h --help Print this messsage and exit
+    --global  Use the global package file and store instead of the project (sync)
 
 COMMANDS:
     tui               Open the TUI for interactively finding and installing
@@ -20,20 +45,47 @@ cli -- If any arguments are not provided then the TUI opens
     search    [OWNER] [REPO] [RELEASE]         Search releases from URL
     search    [OWNER] [REPO] [RELEASE] [ASSET] Search assets from URL
 
+discover -- Needs GITHUB_TOKEN or a token for github.com under [hosts] in the global package file
+    starred               List starred repositories whose latest release has assets for this platform
+    watched               List watched repositories whose latest release has assets for this platform
+
+project -- Uses the nearest .grpm.toml in the current directory or its parents
+    sync                  Install the listed tools into the project's .grpm directory
+    exec      -- CMD...   Run CMD with the project's tools first on PATH
+    env                   Print a shell snippet that puts the project's tools on PATH
+    sync --global         Install ~/.config/grpm/packages.toml into the global store
+
+packages -- The packages installed into the global store
+    changelog PACKAGE [FROM] [TO] Print the release notes after FROM up to TO, by default from the
+                          installed version to the latest release. --compare adds a link comparing them
+
+shims -- Put ~/.local/share/grpm/bin on PATH, each tool there runs the version pinned for
+         the current directory (project store, then a `t:TAG` pin, then the global version)
+    reshim                Regenerate the shims for everything in the global store
+
     RELEASE and ASSET can be one of the following:
-        `[RELEASE] [ASSET]`   may be replaced by `[ASSETID]` for directly finding a certain asset
+        `[RELEASE] [ASSET]`   may be replaced by `[ASSETID]`, all digits, to download a certain asset
         [RELEASE] = latest,   get the latest download
         [RELEASE] = {REGEX},  get first matching a certain regex
         [RELEASE] = t:{TAG},  get a certain tag
+        [RELEASE] = f:{TEXT}, get the best fuzzy match for TEXT
         [ASSET]   = all,      download all Assets
         [ASSET]   = {REGEX},  download all Assets that match a certain regex
+        [ASSET]   = f:{TEXT}, download the Assets that fuzzy match TEXT, eg. f:linux64
 
     OWNER and REPO are the github username and repository name respectively
         you may also provide the suffix of the github url (eg. indianboy42/grpm)
+        or the whole url, a release url (.../releases/tag/TAG) fills in RELEASE and
+        an asset url (.../releases/download/TAG/ASSET) fills in RELEASE and ASSET as well
+        other forges are selected with a prefix or a full URL:
+            gitlab:GROUP/PROJECT, codeberg:OWNER/REPO, https://gitlab.example.com/GROUP/PROJECT
+            gitlab:URL, gitea:URL or forgejo:URL for self-hosted instances on other hosts
 ";
 
-#[derive(Debug, Clone, Copy)]
-pub struct ArgFlags {}
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArgFlags {
+    pub global: bool,
+}
 
 #[derive(Debug)]
 pub struct Args {
@@ -54,37 +106,76 @@ impl Default for Args {
             release: None,
             asset: None,
             install: None,
-            flags: ArgFlags {},
+            flags: ArgFlags::default(),
         }
     }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("Hello, world!");
+    let mut argv: Vec<OsString> = env::args_os().skip(1).collect();
+    // The command run by exec or a shim gets its arguments untouched, pico-args would take flags
+    // such as --help out of them even after a `--`
+    if argv.first().is_some_and(|a| a == "shim") {
+        let mut cmd = argv.split_off(1);
+        if cmd.is_empty() {
+            return Err("No shim given".into());
+        }
+        let exe = cmd.remove(0);
+        let err = shim::exec(&exe.to_string_lossy(), &cmd);
+        eprintln!("{}", err);
+        std::process::exit(127);
+    }
+    if argv.first().is_some_and(|a| a == "exec") {
+        let mut cmd = argv.split_off(1);
+        if cmd.first().is_some_and(|a| a == "--") {
+            cmd.remove(0);
+        }
+        let status = find_project()?.exec(&cmd)?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
 
-    let mut pargs = pico_args::Arguments::from_env();
+    let mut pargs = pico_args::Arguments::from_vec(argv);
     if pargs.contains(["-h", "--help"]) {
         print!("{}", HELP);
         std::process::exit(0);
     }
+    let flags = ArgFlags {
+        global: pargs.contains("--global"),
+    };
+    let compare = pargs.contains("--compare");
 
     let mut arg = || pargs.opt_free_from_str().unwrap();
-    let command = arg().expect("No command given");
+    let command: String = arg().expect("No command given");
+    if command == "changelog" {
+        forge::GitHub::initialise()?;
+        let name: String = arg().ok_or("No PACKAGE given")?;
+        let (from, to) = (arg(), arg());
+        return Runtime::new()?.block_on(cli_changelog(&name, from, to, compare));
+    }
     let owner: Option<String> = arg();
-    let (owner, repo) = if let Some(owner) = owner {
-        if let Some((owner, repo)) = owner.split_once('/') {
+    let link = owner
+        .as_deref()
+        .and_then(|owner| owner.parse::<RepoLink>().ok());
+    let (owner, repo) = match (owner, &link) {
+        (Some(_), Some(link)) => {
+            let spec = link.repo.to_string();
+            let (owner, repo) = spec.split_once('/').expect("RepoSpec displays with a /");
             (Some(owner.to_owned()), Some(repo.to_owned()))
-        } else {
-            (Some(owner), arg())
         }
-    } else {
-        (None, None)
+        (Some(owner), None) => (Some(owner), arg()),
+        (None, _) => (None, None),
     };
-    let release = arg();
-    let asset = arg();
+    // A release or asset link already fills in some of the positional arguments
+    let release = link
+        .as_ref()
+        .and_then(RepoLink::release_selector)
+        .or_else(&mut arg);
+    let asset = link
+        .as_ref()
+        .and_then(RepoLink::asset_regex)
+        .or_else(&mut arg);
     let install = arg();
 
-    let dargs = Args::default();
     let args = Args {
         command,
         owner,
@@ -92,19 +183,147 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         release,
         asset,
         install,
-        flags: ArgFlags {},
+        flags,
     };
 
+    forge::GitHub::initialise()?;
     if args.command.as_str() == "tui" {
         return tuiapp::tui(args);
     }
     match args.command.as_str() {
-        "install" => todo!("CLI install"),
+        "install" => Runtime::new()?.block_on(cli_install(&args)),
+        "download" => Runtime::new()?.block_on(cli_download(&args)),
+        "save" => {
+            let (name, pkg) = cli_package(&args, false)?;
+            let location = args.install.as_deref().map(commands::expand_home);
+            let path = commands::save(location.as_deref(), &name, pkg)?;
+            eprintln!("Saved {} to {}", name, path.display());
+            Ok(())
+        }
         "search" => todo!("CLI search"),
+        "starred" => Runtime::new()?.block_on(cli_installable(false)),
+        "watched" => Runtime::new()?.block_on(cli_installable(true)),
+        "sync" if args.flags.global => Runtime::new()?.block_on(sync_global()),
+        "sync" => Runtime::new()?.block_on(find_project()?.sync()),
+        "reshim" => Ok(shim::reshim(&commands::global_store()?)?),
+        "env" => {
+            let path = find_project()?.path_var()?;
+            let mut line = b"export PATH=".to_vec();
+            line.extend(shell_quote(path.as_bytes()));
+            line.push(b'\n');
+            Ok(io::stdout().write_all(&line)?)
+        }
         _ => panic!("Invalid Command"),
     }
 }
 
-fn foo(x: &str, y: usize, z: bool){
+fn find_project() -> Result<Project, Box<dyn std::error::Error>> {
+    Project::find_from_cwd()?.ok_or_else(|| format!("No {} found", project::MANIFEST).into())
+}
+
+/// A single-quoted sh word, byte for byte so non UTF-8 paths survive
+fn shell_quote(s: &[u8]) -> Vec<u8> {
+    let mut quoted = vec![b'\''];
+    for &b in s {
+        match b {
+            b'\'' => quoted.extend(br"'\''"),
+            b => quoted.push(b),
+        }
+    }
+    quoted.push(b'\'');
+    quoted
+}
 
+/// `[INSTALL-CMD]` shares its position with `[LOCATION]`
+fn cli_package(
+    args: &Args,
+    with_install: bool,
+) -> Result<(String, config::Package), Box<dyn std::error::Error>> {
+    let (owner, repo) = match (&args.owner, &args.repo) {
+        (Some(owner), Some(repo)) => (owner, repo),
+        _ => return Err("OWNER and REPO are required".into()),
+    };
+    commands::package(
+        owner,
+        repo,
+        args.release.as_deref(),
+        args.asset.as_deref(),
+        args.install.as_deref().filter(|_| with_install),
+    )
+}
+
+async fn cli_install(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let (name, pkg) = cli_package(args, true)?;
+    let tag = commands::install(&name, &pkg, common::no_progress()).await?;
+    eprintln!("{} {}", name, tag);
+    Ok(())
+}
+
+async fn cli_download(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, pkg) = cli_package(args, false)?;
+    let src = pkg.repo.parse::<forge::RepoSpec>()?.source()?;
+    // `[ASSETID]` takes the place of `[RELEASE] [ASSET]`, so `[LOCATION]` comes right after it
+    if let Some(id) = args.release.as_deref().and_then(|r| r.parse().ok()) {
+        let ass = AssetFinder::ById(id)
+            .find(&*src)
+            .await?
+            .ok_or_else(|| format!("No asset {} in {}", id, pkg.repo))?;
+        let location = commands::expand_home(args.asset.as_deref().unwrap_or("."));
+        let path = commands::download(&pkg.repo, &ass, &location, common::no_progress()).await?;
+        eprintln!("{}", path.display());
+        return Ok(());
+    }
+    let rel = pkg
+        .release_finder()?
+        .find(&*src)
+        .await?
+        .ok_or("No matching release")?;
+    let location = commands::expand_home(args.install.as_deref().unwrap_or("."));
+    for ass in pkg.find_assets(&rel.assets)? {
+        let path = commands::download(&pkg.repo, &ass, &location, common::no_progress()).await?;
+        eprintln!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// `OWNER/REPO TAG ASSETS...`, one repository per line
+async fn cli_installable(watched: bool) -> Result<(), Box<dyn std::error::Error>> {
+    for found in commands::installable(watched).await? {
+        let assets = found.assets.iter().map(|ass| ass.name.as_str()).collect::<Vec<_>>();
+        println!(
+            "{}/{}\t{}\t{}",
+            found.summary.owner,
+            found.summary.repo,
+            found.tag,
+            assets.join(" ")
+        );
+    }
+    Ok(())
+}
+
+async fn cli_changelog(
+    name: &str,
+    from: Option<String>,
+    to: Option<String>,
+    compare: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pkg = commands::installed_package(name)?;
+    let from = match from {
+        Some(from) => from,
+        None => commands::global_store()?
+            .current(name)
+            .ok_or_else(|| format!("{} is not installed, give FROM", name))?,
+    };
+    let log = commands::changelog(&pkg.repo, &from, to.as_deref()).await?;
+    print!("{}", log.markdown(compare));
+    Ok(())
+}
+
+async fn sync_global() -> Result<(), Box<dyn std::error::Error>> {
+    let store = commands::global_store()?;
+    for (name, pkg) in &Config::load_global()?.packages {
+        let tag = store.install(name, pkg, common::no_progress()).await?;
+        eprintln!("{} {}", name, tag);
+    }
+    Ok(())
 }
@@ -0,0 +1,201 @@
+//! Release notes are Markdown, this turns them into styled `tui` text
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use std::mem;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+};
+
+/// Headings, emphasis, lists, block quotes, code and links; anything else is shown as plain text
+pub fn render(markdown: &str) -> Text<'static> {
+    let mut r = Renderer::default();
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    for event in Parser::new_ext(markdown, options) {
+        r.event(event);
+    }
+    r.flush();
+    Text::from(r.lines)
+}
+
+#[derive(Default)]
+struct Renderer {
+    lines: Vec<Spans<'static>>,
+    line: Vec<Span<'static>>,
+    styles: Vec<Style>,
+    /// The next number of each nested list, `None` for bullets
+    lists: Vec<Option<u64>>,
+    /// Destination of the link being rendered
+    link: Option<String>,
+    code_block: bool,
+    quote: usize,
+}
+
+impl Renderer {
+    fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or_default()
+    }
+    fn push_style(&mut self, f: impl FnOnce(Style) -> Style) {
+        self.styles.push(f(self.style()))
+    }
+
+    fn text(&mut self, text: impl Into<String>, style: Style) {
+        if self.line.is_empty() && self.quote > 0 {
+            let bar = Style::default().fg(Color::DarkGray);
+            self.line.push(Span::styled("│ ".repeat(self.quote), bar));
+        }
+        self.line.push(Span::styled(text.into(), style));
+    }
+    /// End the line, even if it is empty
+    fn newline(&mut self) {
+        self.lines.push(Spans::from(mem::take(&mut self.line)));
+    }
+    /// End the line if anything is on it
+    fn flush(&mut self) {
+        if !self.line.is_empty() {
+            self.newline();
+        }
+    }
+    /// A blank line between blocks, but not before the first one
+    fn gap(&mut self) {
+        self.flush();
+        if self.lines.last().is_some_and(|l| !l.0.is_empty()) {
+            self.newline();
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) if self.code_block => {
+                for (i, line) in text.trim_end_matches('\n').split('\n').enumerate() {
+                    if i > 0 {
+                        self.newline();
+                    }
+                    self.text(line, self.style());
+                }
+            }
+            Event::Text(text) => self.text(text.to_string(), self.style()),
+            Event::Code(code) => {
+                let style = self.style().fg(Color::Yellow);
+                self.text(code.to_string(), style)
+            }
+            Event::Html(html) => {
+                let style = self.style().fg(Color::DarkGray);
+                self.text(html.trim_end().to_owned(), style)
+            }
+            Event::FootnoteReference(label) => self.text(format!("[{}]", label), self.style()),
+            Event::SoftBreak => self.text(" ", self.style()),
+            Event::HardBreak => self.newline(),
+            Event::Rule => {
+                self.gap();
+                self.text("─".repeat(20), Style::default().fg(Color::DarkGray));
+                self.newline();
+            }
+            Event::TaskListMarker(done) => {
+                self.text(if done { "[x] " } else { "[ ] " }, self.style())
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            // Items of loose lists wrap their text in paragraphs
+            Tag::Paragraph if !self.lists.is_empty() => {}
+            Tag::Paragraph => self.gap(),
+            Tag::Heading(level) => {
+                self.gap();
+                self.push_style(|s| {
+                    let s = s.fg(Color::Cyan).add_modifier(Modifier::BOLD);
+                    if level == 1 {
+                        s.add_modifier(Modifier::UNDERLINED)
+                    } else {
+                        s
+                    }
+                });
+            }
+            Tag::BlockQuote => {
+                self.gap();
+                self.quote += 1;
+                self.push_style(|s| s.add_modifier(Modifier::ITALIC));
+            }
+            Tag::CodeBlock(_) => {
+                self.gap();
+                self.code_block = true;
+                self.push_style(|s| s.fg(Color::Yellow));
+            }
+            Tag::List(start) => {
+                if self.lists.is_empty() {
+                    self.gap();
+                } else {
+                    self.flush();
+                }
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}{}. ", indent, *n - 1)
+                    }
+                    _ => format!("{}• ", indent),
+                };
+                self.text(marker, Style::default().fg(Color::DarkGray));
+            }
+            Tag::Emphasis => self.push_style(|s| s.add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self.push_style(|s| s.add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => self.push_style(|s| s.add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link(_, url, _) | Tag::Image(_, url, _) => {
+                self.link = Some(url.to_string());
+                self.push_style(|s| s.fg(Color::Blue).add_modifier(Modifier::UNDERLINED));
+            }
+            Tag::FootnoteDefinition(label) => {
+                self.gap();
+                self.text(format!("[{}]: ", label), self.style());
+            }
+            Tag::Table(_) | Tag::TableHead | Tag::TableRow | Tag::TableCell => {}
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::Item | Tag::FootnoteDefinition(_) => self.flush(),
+            Tag::Heading(_) => {
+                self.flush();
+                self.styles.pop();
+            }
+            Tag::BlockQuote => {
+                self.flush();
+                self.quote -= 1;
+                self.styles.pop();
+            }
+            Tag::CodeBlock(_) => {
+                self.flush();
+                self.code_block = false;
+                self.styles.pop();
+            }
+            Tag::List(_) => {
+                self.flush();
+                self.lists.pop();
+            }
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough => {
+                self.styles.pop();
+            }
+            Tag::Link(..) | Tag::Image(..) => {
+                self.styles.pop();
+                // Autolinks already show the destination
+                let url = self.link.take().unwrap_or_default();
+                let shown = self.line.last().is_some_and(|s| s.content == url);
+                if !shown && !url.starts_with('#') {
+                    let style = Style::default().fg(Color::DarkGray);
+                    self.text(format!(" <{}>", url), style);
+                }
+            }
+            Tag::TableCell => self.text(" │ ", self.style()),
+            Tag::TableHead | Tag::TableRow => self.flush(),
+            Tag::Table(_) => {}
+        }
+    }
+}
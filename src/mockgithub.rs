@@ -0,0 +1,393 @@
+//! A local stand-in for the GitHub releases API and asset downloads, serving `tests/fixtures/github`
+//!
+//! Each repository is a directory `OWNER/REPO` with `releases.json`, its releases newest first in
+//! a short form that is filled out to what GitHub answers, and `assets/` with the files to download:
+//!
+//! ```json
+//! [{ "tag_name": "v1.0.0", "body": "Notes", "prerelease": false, "assets": ["tool-linux"] }]
+//! ```
+//!
+//! The API answers under `/api/v3/` like GitHub Enterprise, so [`GitHub::enterprise`] points at it
+//! through the `api` of a `[hosts]` entry, and the global `octocrab` instance behind github.com
+//! through its base URL. Asset links redirect to `/storage/` like github.com's do
+use serde_json::{json, Value};
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::{Arc, Mutex, Once},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use url::Url;
+
+use crate::{config::HostConfig, forge::GitHub};
+
+#[derive(Debug, Default)]
+struct Settings {
+    /// Caps `per_page` the way some servers do, GitHub caps it at 100
+    max_per_page: Option<usize>,
+    /// API requests answered before every further one is rate limited
+    rate_limit: Option<usize>,
+    /// Path and query of every request, in order
+    requests: Vec<String>,
+}
+
+pub struct MockGitHub {
+    /// `http://127.0.0.1:PORT/`
+    pub url: Url,
+    settings: Arc<Mutex<Settings>>,
+}
+
+/// Sources made from a `RepoSpec` read the `[hosts]` of the global package file, point
+/// `XDG_CONFIG_HOME` at an empty directory so that the developer's own config stays out of the tests
+fn isolate_config() {
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let dir = env::temp_dir().join(format!("grpm-test-config-{}", process::id()));
+        env::set_var("XDG_CONFIG_HOME", dir);
+    });
+}
+
+/// An HTTP response before it is written out
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: &Value) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type", String::from("application/json"))],
+            body: value.to_string().into_bytes(),
+        }
+    }
+    /// GitHub's error document
+    fn error(status: u16, message: &str) -> Self {
+        let docs = "https://docs.github.com/rest";
+        Self::json(
+            status,
+            &json!({ "message": message, "documentation_url": docs }),
+        )
+    }
+    fn not_found() -> Self {
+        Self::error(404, "Not Found")
+    }
+}
+
+impl MockGitHub {
+    /// Listen on a free port of the loopback interface, requests are answered by a spawned task
+    pub async fn start() -> Self {
+        isolate_config();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let mock = Self {
+            url: url.parse().unwrap(),
+            settings: Arc::default(),
+        };
+        let (base, settings) = (mock.url.clone(), mock.settings.clone());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, base.clone(), settings.clone()));
+            }
+        });
+        mock
+    }
+
+    pub fn max_per_page(&self, per: usize) -> &Self {
+        self.settings.lock().unwrap().max_per_page = Some(per);
+        self
+    }
+    /// Answer `requests` more API requests, then only `403 API rate limit exceeded`
+    pub fn rate_limit(&self, requests: usize) -> &Self {
+        self.settings.lock().unwrap().rate_limit = Some(requests);
+        self
+    }
+    /// Path and query of every request so far
+    pub fn requests(&self) -> Vec<String> {
+        self.settings.lock().unwrap().requests.clone()
+    }
+
+    pub fn api(&self) -> Url {
+        self.url.join("api/v3/").unwrap()
+    }
+    /// The repository as a package's `repo`, unknown hosts are GitHub Enterprise
+    pub fn repo(&self, owner: &str, repo: &str) -> String {
+        format!("{}{}/{}", self.url, owner, repo)
+    }
+    /// The release source for a fixture repository, configured the way a `[hosts]` entry would
+    pub fn source(&self, owner: &str, repo: &str) -> GitHub {
+        let conf = HostConfig {
+            api: Some(self.api()),
+            ..HostConfig::default()
+        };
+        GitHub::enterprise(&self.url, Some(&conf), owner, repo).unwrap()
+    }
+}
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/github")
+}
+
+/// One request per connection, that is all the clients need
+async fn serve(mut stream: TcpStream, base: Url, settings: Arc<Mutex<Settings>>) {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+    while !head.ends_with(b"\r\n\r\n") {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+        }
+    }
+    let head = String::from_utf8_lossy(&head);
+    let target = head.split_whitespace().nth(1).unwrap_or("/").to_owned();
+    let raw = head
+        .lines()
+        .any(|line| line.eq_ignore_ascii_case("accept: application/octet-stream"));
+    let response = respond(&target, raw, &base, &settings);
+
+    let mut out = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason(response.status),
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str("\r\n");
+    let _ = stream.write_all(out.as_bytes()).await;
+    let _ = stream.write_all(&response.body).await;
+    let _ = stream.shutdown().await;
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        302 => "Found",
+        403 => "Forbidden",
+        404 => "Not Found",
+        _ => "",
+    }
+}
+
+/// `raw` asks for an asset's file rather than its JSON
+fn respond(target: &str, raw: bool, base: &Url, settings: &Mutex<Settings>) -> Response {
+    let url = base.join(target).unwrap();
+    let query = |name: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .and_then(|(_, v)| v.parse::<usize>().ok())
+    };
+    let mut settings = settings.lock().unwrap();
+    settings.requests.push(target.to_owned());
+    let segments = url.path_segments().unwrap().collect::<Vec<_>>();
+
+    let api = match segments.as_slice() {
+        ["api", "v3", api @ ..] => api,
+        // Asset links redirect to the storage like github.com's do
+        [owner, repo, "releases", "download", tag, name] => {
+            let location = base
+                .join(&format!("storage/{}/{}/{}/{}", owner, repo, tag, name))
+                .unwrap();
+            return Response {
+                status: 302,
+                headers: vec![("Location", location.to_string())],
+                body: Vec::new(),
+            };
+        }
+        ["storage", owner, repo, _tag, name] => {
+            let path = fixtures().join(owner).join(repo).join("assets").join(name);
+            return match fs::read(path) {
+                Ok(body) => Response {
+                    status: 200,
+                    headers: vec![("Content-Type", String::from("application/octet-stream"))],
+                    body,
+                },
+                Err(_) => Response::not_found(),
+            };
+        }
+        _ => return Response::not_found(),
+    };
+
+    if let Some(left) = settings.rate_limit {
+        if left == 0 {
+            let mut response = Response::error(
+                403,
+                "API rate limit exceeded for 127.0.0.1. (But here's the good news: \
+                 Authenticated requests get a higher rate limit.)",
+            );
+            response.headers.extend(vec![
+                ("X-RateLimit-Limit", String::from("60")),
+                ("X-RateLimit-Remaining", String::from("0")),
+                ("X-RateLimit-Reset", String::from("1624060800")),
+            ]);
+            return response;
+        }
+        settings.rate_limit = Some(left - 1);
+    }
+
+    let (owner, repo, rest) = match api {
+        ["repos", owner, repo, "releases", rest @ ..] => (*owner, *repo, rest),
+        _ => return Response::not_found(),
+    };
+    let releases = match load_releases(base, owner, repo) {
+        Some(releases) => releases,
+        None => return Response::not_found(),
+    };
+    let found = |found: Option<&Value>| match found {
+        Some(value) => Response::json(200, value),
+        None => Response::not_found(),
+    };
+    match rest {
+        [] => {
+            let per = query("per_page").unwrap_or(30).min(100);
+            let per = settings.max_per_page.map_or(per, |max| per.min(max)).max(1);
+            let page = query("page").unwrap_or(1).max(1);
+            let last = (releases.len().max(1) - 1) / per + 1;
+            let items = releases
+                .iter()
+                .skip((page - 1) * per)
+                .take(per)
+                .cloned()
+                .collect::<Vec<_>>();
+            let mut response = Response::json(200, &Value::Array(items));
+            let link = |page: usize, rel: &str| {
+                let mut url = url.clone();
+                url.query_pairs_mut()
+                    .clear()
+                    .append_pair("per_page", &per.to_string())
+                    .append_pair("page", &page.to_string());
+                format!("<{}>; rel=\"{}\"", url, rel)
+            };
+            let mut links = Vec::new();
+            if page < last {
+                links.push(link(page + 1, "next"));
+                links.push(link(last, "last"));
+            }
+            if page > 1 {
+                links.push(link(1, "first"));
+                links.push(link(page - 1, "prev"));
+            }
+            if !links.is_empty() {
+                response.headers.push(("Link", links.join(", ")));
+            }
+            response
+        }
+        ["latest"] => found(
+            releases
+                .iter()
+                .find(|rel| rel["prerelease"] == false && rel["draft"] == false),
+        ),
+        ["tags", tag] => found(releases.iter().find(|rel| rel["tag_name"] == *tag)),
+        ["assets", id] => {
+            let id = id.parse::<u64>().ok();
+            let asset = releases
+                .iter()
+                .flat_map(|rel| rel["assets"].as_array().unwrap())
+                .find(|ass| ass["id"].as_u64() == id);
+            match asset {
+                // The file itself is a redirect to the storage
+                Some(asset) if raw => Response {
+                    status: 302,
+                    headers: vec![(
+                        "Location",
+                        asset["browser_download_url"].as_str().unwrap().to_owned(),
+                    )],
+                    body: Vec::new(),
+                },
+                asset => found(asset),
+            }
+        }
+        _ => Response::not_found(),
+    }
+}
+
+/// The releases of a fixture repository as GitHub answers them, `None` if there is no such fixture
+fn load_releases(base: &Url, owner: &str, repo: &str) -> Option<Vec<Value>> {
+    let dir = fixtures().join(owner).join(repo);
+    let short: Vec<Value> =
+        serde_json::from_str(&fs::read_to_string(dir.join("releases.json")).ok()?).unwrap();
+    let api = base.join("api/v3/").unwrap();
+    let user = json!({
+        "login": "octocat",
+        "id": 1,
+        "node_id": "MDQ6VXNlcjE=",
+        "avatar_url": format!("{}avatars/octocat", base),
+        "gravatar_id": "",
+        "url": format!("{}users/octocat", api),
+        "html_url": format!("{}octocat", base),
+        "followers_url": format!("{}users/octocat/followers", api),
+        "following_url": format!("{}users/octocat/following", api),
+        "gists_url": format!("{}users/octocat/gists", api),
+        "starred_url": format!("{}users/octocat/starred", api),
+        "subscriptions_url": format!("{}users/octocat/subscriptions", api),
+        "organizations_url": format!("{}users/octocat/orgs", api),
+        "repos_url": format!("{}users/octocat/repos", api),
+        "events_url": format!("{}users/octocat/events", api),
+        "received_events_url": format!("{}users/octocat/received_events", api),
+        "type": "User",
+        "site_admin": false,
+    });
+    let repo_api = format!("{}repos/{}/{}", api, owner, repo);
+    let releases = short.iter().enumerate().map(|(i, rel)| {
+        let id = i + 1;
+        let tag = rel["tag_name"].as_str().unwrap();
+        let published = rel
+            .get("published_at")
+            .cloned()
+            .unwrap_or_else(|| json!("2021-01-01T00:00:00Z"));
+        let assets = rel["assets"].as_array().cloned().unwrap_or_default();
+        let assets = assets.iter().enumerate().map(|(j, name)| {
+            let name = name.as_str().unwrap();
+            let size = fs::metadata(dir.join("assets").join(name)).map_or(0, |m| m.len());
+            let asset_id = id * 100 + j;
+            json!({
+                "url": format!("{}/releases/assets/{}", repo_api, asset_id),
+                "browser_download_url":
+                    format!("{}{}/{}/releases/download/{}/{}", base, owner, repo, tag, name),
+                "id": asset_id,
+                "node_id": format!("RA_{}", asset_id),
+                "name": name,
+                "label": null,
+                "state": "uploaded",
+                "content_type": "application/octet-stream",
+                "size": size,
+                "download_count": 0,
+                "created_at": published,
+                "updated_at": published,
+                "uploader": user,
+            })
+        });
+        json!({
+            "url": format!("{}/releases/{}", repo_api, id),
+            "html_url": format!("{}{}/{}/releases/tag/{}", base, owner, repo, tag),
+            "assets_url": format!("{}/releases/{}/assets", repo_api, id),
+            "upload_url": format!(
+                "{}api/uploads/repos/{}/{}/releases/{}/assets{{?name,label}}",
+                base, owner, repo, id
+            ),
+            "tarball_url": format!("{}/tarball/{}", repo_api, tag),
+            "zipball_url": format!("{}/zipball/{}", repo_api, tag),
+            "id": id,
+            "node_id": format!("RE_{}", id),
+            "tag_name": tag,
+            "target_commitish": "main",
+            "name": rel.get("name").cloned().unwrap_or(Value::Null),
+            "body": rel.get("body").cloned().unwrap_or(Value::Null),
+            "draft": rel.get("draft").cloned().unwrap_or(json!(false)),
+            "prerelease": rel.get("prerelease").cloned().unwrap_or(json!(false)),
+            "created_at": published,
+            "published_at": published,
+            "author": user,
+            "assets": assets.collect::<Vec<_>>(),
+        })
+    });
+    Some(releases.collect())
+}
+
+mod tests;
@@ -0,0 +1,283 @@
+//! Listing, finding, downloading and installing releases against the mock GitHub
+use octocrab::Octocrab;
+use regex::Regex;
+use std::{env, fs, path::PathBuf, process::Command};
+
+use super::MockGitHub;
+use crate::{
+    commands,
+    common::{self, AssetFinder, ReleaseFinder},
+    config::{Config, HostConfig, Package},
+    detect::Platform,
+    forge::{GitHub, ReleaseSource, RepoSpec},
+    store::Store,
+};
+
+fn tags(rels: &[crate::forge::Release]) -> Vec<&str> {
+    rels.iter().map(|rel| rel.tag_name.as_str()).collect()
+}
+
+/// Removes the directory when the test ends, failed or not
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn release_pages(mock: &MockGitHub) -> Vec<String> {
+    let requests = mock.requests().into_iter();
+    requests.filter(|r| r.contains("/releases?")).collect()
+}
+
+#[tokio::test]
+async fn lists_every_release_following_the_link_headers() {
+    let mock = MockGitHub::start().await;
+    mock.max_per_page(2);
+    let rels = mock.source("owner", "tool").list_releases().await.unwrap();
+    assert_eq!(
+        tags(&rels),
+        ["v1.3.0-rc1", "v1.2.0", "v1.1.0", "v1.0.1", "v1.0.0"]
+    );
+    assert_eq!(release_pages(&mock).len(), 3);
+
+    let rel = &rels[1];
+    assert_eq!(rel.body.as_deref(), Some("## Features\n\n- Faster startup"));
+    let names = rel
+        .assets
+        .iter()
+        .map(|ass| ass.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "tool-v1.2.0-x86_64-linux",
+            "tool-v1.2.0-x86_64-macos",
+            "tool-v1.2.0.sha256"
+        ]
+    );
+    assert_eq!(rel.assets[0].size, Some(26));
+}
+
+#[tokio::test]
+async fn a_page_past_the_end_is_empty() {
+    let mock = MockGitHub::start().await;
+    let src = mock.source("owner", "tool");
+    assert_eq!(src.list_releases_page(1, 3).await.unwrap().len(), 3);
+    assert_eq!(src.list_releases_page(2, 3).await.unwrap().len(), 2);
+    assert!(src.list_releases_page(3, 3).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn an_unknown_repository_fails() {
+    let mock = MockGitHub::start().await;
+    assert!(mock
+        .source("owner", "nothing")
+        .list_releases()
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn the_latest_release_is_not_a_prerelease() {
+    let mock = MockGitHub::start().await;
+    let rel = ReleaseFinder::Latest
+        .find(&mock.source("owner", "tool"))
+        .await
+        .unwrap();
+    assert_eq!(rel.unwrap().tag_name, "v1.2.0");
+}
+
+#[tokio::test]
+async fn the_github_com_source_uses_the_instance_s_base_url() {
+    let mock = MockGitHub::start().await;
+    let builder = Octocrab::builder().base_url(mock.api()).unwrap();
+    octocrab::initialise(builder).unwrap();
+    let rel = ReleaseFinder::Latest
+        .find(&GitHub::new("owner", "tool"))
+        .await
+        .unwrap();
+    assert_eq!(rel.unwrap().tag_name, "v1.2.0");
+    assert_eq!(
+        mock.requests(),
+        ["/api/v3/repos/owner/tool/releases/latest"]
+    );
+}
+
+#[tokio::test]
+async fn finds_releases_by_tag() {
+    let mock = MockGitHub::start().await;
+    let src = mock.source("owner", "tool");
+    let found = ReleaseFinder::ByTag(String::from("v1.1.0"))
+        .find(&src)
+        .await
+        .unwrap();
+    assert_eq!(found.unwrap().tag_name, "v1.1.0");
+    // GitHub's 404 is no release rather than an error
+    let missing = ReleaseFinder::ByTag(String::from("v9.9.9"))
+        .find(&src)
+        .await
+        .unwrap();
+    assert_eq!(missing, None);
+}
+
+#[tokio::test]
+async fn the_regex_finder_stops_at_the_first_page_with_a_match() {
+    let mock = MockGitHub::start().await;
+    mock.max_per_page(2);
+    let found = ReleaseFinder::ByRegex(Regex::new(r"^v1\.1").unwrap())
+        .find(&mock.source("owner", "tool"))
+        .await
+        .unwrap();
+    assert_eq!(found.unwrap().tag_name, "v1.1.0");
+    assert_eq!(release_pages(&mock).len(), 2);
+}
+
+#[tokio::test]
+async fn the_fuzzy_finder_ranks_every_page() {
+    let mock = MockGitHub::start().await;
+    mock.max_per_page(2);
+    let src = mock.source("owner", "tool");
+    // The first page only has weak matches
+    let found = ReleaseFinder::Fuzzy(String::from("v1.0"))
+        .find(&src)
+        .await
+        .unwrap();
+    assert_eq!(found.unwrap().tag_name, "v1.0.1");
+    assert_eq!(release_pages(&mock).len(), 4);
+
+    // Until an exact tag
+    let found = ReleaseFinder::Fuzzy(String::from("v1.2.0"))
+        .find(&src)
+        .await
+        .unwrap();
+    assert_eq!(found.unwrap().tag_name, "v1.2.0");
+    assert_eq!(release_pages(&mock).len(), 5);
+}
+
+#[tokio::test]
+async fn installable_releases_are_looked_for_past_the_first_page() {
+    let mock = MockGitHub::start().await;
+    mock.max_per_page(2);
+    let src = mock.source("owner", "tool");
+    let host = Platform::from_name("x86_64-linux");
+    let linux = commands::fitting_release(&src, &host);
+    let (tag, assets) = linux.await.unwrap().unwrap();
+    // The release candidate and the checksum are passed over
+    assert_eq!(tag, "v1.2.0");
+    let names = assets.iter().map(|ass| ass.name.as_str());
+    assert_eq!(names.collect::<Vec<_>>(), ["tool-v1.2.0-x86_64-linux"]);
+    assert_eq!(release_pages(&mock).len(), 1);
+
+    let host = Platform::from_name("x86_64-freebsd");
+    let freebsd = commands::fitting_release(&src, &host);
+    assert_eq!(freebsd.await.unwrap().unwrap().0, "v1.0.0");
+    let host = Platform::from_name("x86_64-windows");
+    let windows = commands::fitting_release(&src, &host);
+    assert_eq!(windows.await.unwrap(), None);
+    // v1.0.0 is on the last page, nothing fitting windows takes the empty one after it too
+    assert_eq!(release_pages(&mock).len(), 1 + 3 + 4);
+}
+
+#[tokio::test]
+async fn finds_assets_by_regex_and_id() {
+    let mock = MockGitHub::start().await;
+    let src = mock.source("owner", "tool");
+    let by_regex = AssetFinder::ByRegex(ReleaseFinder::Latest, Regex::new("macos").unwrap());
+    let ass = by_regex.find(&src).await.unwrap().unwrap();
+    assert_eq!(ass.name, "tool-v1.2.0-x86_64-macos");
+
+    let by_id = AssetFinder::ById(ass.id).find(&src).await.unwrap();
+    assert_eq!(by_id, Some(ass));
+    assert_eq!(AssetFinder::ById(1).find(&src).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn rate_limited_requests_fail_with_github_s_message() {
+    let mock = MockGitHub::start().await;
+    mock.rate_limit(1);
+    let src = mock.source("owner", "tool");
+    assert!(src.get_latest().await.is_ok());
+    let err = src.get_latest().await.unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("API rate limit exceeded"),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn downloads_follow_the_redirect_to_the_storage() {
+    let mock = MockGitHub::start().await;
+    let src = mock.source("owner", "tool");
+    let rel = src.get_by_tag("v1.1.0").await.unwrap().unwrap();
+    let bytes = common::download_asset(rel.assets[0].clone(), common::no_progress())
+        .await
+        .unwrap();
+    assert_eq!(bytes, b"#!/bin/sh\necho tool 1.1.0\n");
+    let requests = mock.requests();
+    let last = &requests[requests.len() - 2..];
+    assert_eq!(
+        last,
+        [
+            "/owner/tool/releases/download/v1.1.0/tool-v1.1.0-x86_64-linux",
+            "/storage/owner/tool/v1.1.0/tool-v1.1.0-x86_64-linux"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn a_token_downloads_through_the_api() {
+    let mock = MockGitHub::start().await;
+    let conf = HostConfig {
+        api: Some(mock.api()),
+        token: Some(String::from("secret")),
+        ..HostConfig::default()
+    };
+    let src = GitHub::enterprise(&mock.url, Some(&conf), "owner", "tool").unwrap();
+    let rel = src.get_by_tag("v1.1.0").await.unwrap().unwrap();
+    let bytes = src
+        .download_asset(&rel.assets[0], common::no_progress())
+        .await
+        .unwrap();
+    assert_eq!(bytes, b"#!/bin/sh\necho tool 1.1.0\n");
+    let requests = mock.requests();
+    assert_eq!(
+        requests[requests.len() - 3],
+        format!(
+            "/api/v3/repos/owner/tool/releases/assets/{}",
+            rel.assets[0].id
+        )
+    );
+}
+
+#[tokio::test]
+async fn installs_the_latest_release_into_a_store() {
+    let mock = MockGitHub::start().await;
+    let root = TempDir(env::temp_dir().join(format!("grpm-test-{}", mock.url.port().unwrap())));
+    let store = Store::at(&root.0);
+    // Not the developer's `[hosts]`
+    assert!(Config::global_path().unwrap().starts_with(env::temp_dir()));
+    let pkg = Package {
+        // The way a saved package has it
+        repo: mock
+            .repo("owner", "tool")
+            .parse::<RepoSpec>()
+            .unwrap()
+            .to_string(),
+        release: None,
+        asset: Some(String::from("x86_64-linux$")),
+        install: None,
+        bin: vec![String::from("tool")],
+    };
+    let tag = store
+        .install("tool", &pkg, common::no_progress())
+        .await
+        .unwrap();
+    assert_eq!(tag, "v1.2.0");
+    assert_eq!(store.current("tool").as_deref(), Some("v1.2.0"));
+    assert_eq!(store.package("tool"), Some(pkg));
+    let output = Command::new(store.bin_dir().join("tool")).output().unwrap();
+    assert_eq!(output.stdout, b"tool 1.2.0\n");
+}
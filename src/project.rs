@@ -0,0 +1,72 @@
+use std::{
+    env,
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+use crate::{common, config::Config, shim, store::Store};
+
+/// File name of the per-project manifest, same format as the global package file
+pub const MANIFEST: &str = ".grpm.toml";
+/// Project-scoped install store, next to the manifest
+pub const STORE_DIR: &str = ".grpm";
+
+#[derive(Debug, Clone)]
+pub struct Project {
+    root: PathBuf,
+    pub config: Config,
+}
+
+impl Project {
+    /// Look for a manifest in `from` and each of its parents
+    pub fn find(from: &Path) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        match from.ancestors().find(|dir| dir.join(MANIFEST).is_file()) {
+            Some(root) => Ok(Some(Self {
+                root: root.to_owned(),
+                config: Config::load(&root.join(MANIFEST))?,
+            })),
+            None => Ok(None),
+        }
+    }
+    pub fn find_from_cwd() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        Self::find(&env::current_dir()?)
+    }
+
+    pub fn store(&self) -> Store {
+        Store::at(self.root.join(STORE_DIR))
+    }
+
+    /// Install every tool listed in the manifest into the project store,
+    /// and make sure the global bin directory has a shim for each of them
+    pub async fn sync(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let store = self.store();
+        let global = Store::global();
+        for (name, pkg) in &self.config.packages {
+            let tag = store.install(name, pkg, common::no_progress()).await?;
+            if let Some(global) = &global {
+                shim::write_all(&global.bin_dir(), &store.executables(name, &tag, &pkg.bin)?)?;
+            }
+            eprintln!("{} {}", name, tag);
+        }
+        Ok(())
+    }
+
+    /// `PATH` with the project bin directory in front
+    pub fn path_var(&self) -> Result<OsString, env::JoinPathsError> {
+        let current = env::var_os("PATH").unwrap_or_default();
+        env::join_paths(std::iter::once(self.store().bin_dir()).chain(env::split_paths(&current)))
+    }
+
+    /// Run `cmd` with the project tools first on `PATH`
+    pub fn exec(&self, cmd: &[OsString]) -> io::Result<ExitStatus> {
+        let (program, args) = cmd
+            .split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No command given"))?;
+        let path = self
+            .path_var()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Command::new(program).args(args).env("PATH", path).status()
+    }
+}
@@ -0,0 +1,105 @@
+//! Shims are tiny scripts in the global bin directory that call back into `grpm shim EXE`,
+//! which picks the installed version pinned for the current directory and execs it.
+//!
+//! Resolution order for `EXE`:
+//! 1. the bin directory of the nearest project store (`grpm sync`)
+//! 2. a version installed in the global store that matches a `t:TAG` pin in the project manifest
+//! 3. the `current` version in the global store
+use std::{
+    env,
+    ffi::OsString,
+    fs, io,
+    os::unix::{fs::PermissionsExt, process::CommandExt},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{common::ReleaseFinder, project::Project, store::Store};
+
+/// Write (or overwrite) the shim for `exe` at `path`
+pub fn write(path: &Path, exe: &str) -> io::Result<()> {
+    let grpm = env::current_exe()?;
+    let script = format!(
+        "#!/bin/sh\n# grpm shim for {exe}\nexec '{grpm}' shim '{exe}' \"$@\"\n",
+        exe = exe.replace('\'', r"'\''"),
+        grpm = grpm.to_string_lossy().replace('\'', r"'\''"),
+    );
+    if fs::symlink_metadata(path).is_ok() {
+        fs::remove_file(path)?;
+    }
+    fs::write(path, script)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+/// Write a shim into `bin_dir` for each of `exes`, named after the file
+pub fn write_all(bin_dir: &Path, exes: &[PathBuf]) -> io::Result<()> {
+    fs::create_dir_all(bin_dir)?;
+    for exe in exes {
+        if let Some(file_name) = exe.file_name() {
+            write(&bin_dir.join(file_name), &file_name.to_string_lossy())?;
+        }
+    }
+    Ok(())
+}
+
+/// Regenerate the shims for everything installed in the global store
+pub fn reshim(global: &Store) -> io::Result<()> {
+    for name in global.packages()? {
+        if let Some(tag) = global.current(&name) {
+            // The same executables as the install, not every one in the release
+            let bin = global.package(&name).map(|pkg| pkg.bin).unwrap_or_default();
+            global.link(&name, &tag, &bin)?;
+        }
+    }
+    Ok(())
+}
+
+/// Find the executable `exe` should dispatch to from `cwd`
+pub fn resolve(exe: &str, cwd: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let global = Store::global();
+
+    if let Some(project) = Project::find(cwd)? {
+        let local = project.store().bin_dir().join(exe);
+        if local.exists() {
+            return Ok(local);
+        }
+        if let Some(global) = &global {
+            for (name, pkg) in &project.config.packages {
+                if let Ok(ReleaseFinder::ByTag(tag)) = pkg.release_finder() {
+                    if let Some(path) = global.find_executable(name, &tag, exe) {
+                        return Ok(path);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(global) = &global {
+        for name in global.packages()? {
+            if let Some(path) = global
+                .current(&name)
+                .and_then(|tag| global.find_executable(&name, &tag, exe))
+            {
+                return Ok(path);
+            }
+        }
+    }
+
+    Err(format!(
+        "grpm: {} is not installed, neither for this project nor globally (try `grpm sync`)",
+        exe
+    )
+    .into())
+}
+
+/// Replace this process with the resolved executable, only returns on failure
+pub fn exec(exe: &str, args: &[OsString]) -> Box<dyn std::error::Error> {
+    let cwd = match env::current_dir() {
+        Ok(cwd) => cwd,
+        Err(e) => return e.into(),
+    };
+    match resolve(exe, &cwd) {
+        Ok(path) => Command::new(path).arg0(exe).args(args).exec().into(),
+        Err(e) => e,
+    }
+}
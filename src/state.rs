@@ -0,0 +1,129 @@
+//! What the TUI remembers between runs, kept apart from the package file as it changes on every run
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+/// Recent repositories kept, favourites are kept regardless
+const RECENT: usize = 20;
+
+/// Recent and favourite repositories with their fields, and what was entered into each field
+///
+/// ```toml
+/// recent = ["github.com/BurntSushi/ripgrep"]
+/// favourites = ["github.com/BurntSushi/ripgrep"]
+///
+/// [repos."github.com/BurntSushi/ripgrep"]
+/// owner = "BurntSushi"
+/// repo = "ripgrep"
+/// asset = "x86_64-unknown-linux-musl"
+///
+/// [history]
+/// asset = ["musl", "x86_64-unknown-linux-musl"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    /// Keys of repositories, see [`RepoSpec::key`](crate::forge::RepoSpec::key), most recently used first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recent: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub favourites: Vec<String>,
+    /// The fields last used with each recent or favourite repository
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub repos: BTreeMap<String, RepoFields>,
+    /// Field name to what was entered into it, oldest first
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub history: BTreeMap<String, Vec<String>>,
+}
+
+/// The Owner, Repo, Release and Asset fields as they were typed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RepoFields {
+    pub owner: String,
+    pub repo: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub release: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub asset: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub fuzzy: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+impl State {
+    /// `~/.local/share/grpm/state.toml`
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|d| d.join("grpm").join("state.toml"))
+    }
+    /// Nothing is remembered while there is no state file
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        match Self::path() {
+            Some(path) if path.exists() => {
+                let s = fs::read_to_string(&path)?;
+                toml::from_str(&s).map_err(|e| format!("{}: {}", path.display(), e).into())
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path().ok_or("No data directory to keep the TUI state in")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The repository of the last session
+    pub fn last(&self) -> Option<&RepoFields> {
+        self.recent.first().and_then(|key| self.repos.get(key))
+    }
+
+    /// Move `key` to the front of the recent repositories, it keeps its filters if it has any
+    pub fn used(&mut self, key: &str, owner: &str, repo: &str) {
+        self.recent.retain(|k| k != key);
+        self.recent.insert(0, key.to_owned());
+        self.recent.truncate(RECENT);
+        let fields = self.repos.entry(key.to_owned()).or_default();
+        fields.owner = owner.to_owned();
+        fields.repo = repo.to_owned();
+        let (recent, favourites) = (&self.recent, &self.favourites);
+        self.repos
+            .retain(|k, _| recent.contains(k) || favourites.contains(k));
+    }
+
+    pub fn is_favourite(&self, key: &str) -> bool {
+        self.favourites.iter().any(|k| k == key)
+    }
+    /// Returns whether `key` is a favourite now
+    pub fn toggle_favourite(&mut self, key: &str) -> bool {
+        if self.is_favourite(key) {
+            self.favourites.retain(|k| k != key);
+            false
+        } else {
+            self.favourites.push(key.to_owned());
+            true
+        }
+    }
+
+    /// Favourites then recent repositories whose Owner and Repo fields contain `owner` and `repo`,
+    /// each with whether it is a favourite
+    pub fn suggestions(&self, owner: &str, repo: &str) -> Vec<(bool, &RepoFields)> {
+        let (owner, repo) = (owner.to_lowercase(), repo.to_lowercase());
+        let favourites = self.favourites.iter().map(|key| (true, key));
+        let recent = self
+            .recent
+            .iter()
+            .filter(|key| !self.is_favourite(key))
+            .map(|key| (false, key));
+        favourites
+            .chain(recent)
+            .filter_map(|(favourite, key)| Some((favourite, self.repos.get(key)?)))
+            .filter(|(_, fields)| {
+                fields.owner.to_lowercase().contains(&owner)
+                    && fields.repo.to_lowercase().contains(&repo)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,316 @@
+use std::{
+    fs, io,
+    os::unix::fs::{symlink, PermissionsExt},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{common, config::Package, detect::Format, forge::RepoSpec, shim};
+
+const PACKAGE_FILE: &str = "package.toml";
+
+/// Versioned install store
+///
+/// ```text
+/// ROOT/pkgs/NAME/TAG/...       unpacked release asset
+/// ROOT/pkgs/NAME/current       -> TAG
+/// ROOT/pkgs/NAME/package.toml  the package entry it was last installed with
+/// ROOT/bin/EXE                 -> ../pkgs/NAME/TAG/.../EXE, or a shim
+/// ```
+#[derive(Debug, Clone)]
+pub struct Store {
+    root: PathBuf,
+    /// Write shims into the bin directory instead of symlinks, see [`shim`]
+    shims: bool,
+}
+
+impl Store {
+    pub fn at(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            shims: false,
+        }
+    }
+    /// `~/.local/share/grpm`, its bin directory holds shims
+    pub fn global() -> Option<Self> {
+        dirs::data_dir().map(|d| Self {
+            root: d.join("grpm"),
+            shims: true,
+        })
+    }
+
+    pub fn bin_dir(&self) -> PathBuf {
+        self.root.join("bin")
+    }
+    pub fn package_dir(&self, name: &str) -> PathBuf {
+        self.root.join("pkgs").join(name)
+    }
+    pub fn version_dir(&self, name: &str, tag: &str) -> PathBuf {
+        // Tags are free-form, keep them to a single path component
+        self.package_dir(name).join(tag.replace('/', "_"))
+    }
+
+    /// Every package with at least one installed version
+    pub fn packages(&self) -> io::Result<Vec<String>> {
+        let dir = self.root.join("pkgs");
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut names = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+    pub fn is_installed(&self, name: &str, tag: &str) -> bool {
+        self.version_dir(name, tag).is_dir()
+    }
+    /// Oldest install first, tags themselves do not sort reliably
+    pub fn installed_versions(&self, name: &str) -> io::Result<Vec<String>> {
+        let dir = self.package_dir(name);
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut tags = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if file_name != "current" && !file_name.starts_with('.') && entry.file_type()?.is_dir()
+            {
+                tags.push((entry.metadata()?.modified()?, file_name));
+            }
+        }
+        tags.sort();
+        Ok(tags.into_iter().map(|(_, tag)| tag).collect())
+    }
+    /// The version that is currently linked into the bin directory
+    pub fn current(&self, name: &str) -> Option<String> {
+        let target = fs::read_link(self.package_dir(name).join("current")).ok()?;
+        Some(target.file_name()?.to_string_lossy().into_owned())
+    }
+    /// The package entry `name` was last installed with
+    pub fn package(&self, name: &str) -> Option<Package> {
+        let s = fs::read_to_string(self.package_dir(name).join(PACKAGE_FILE)).ok()?;
+        toml::from_str(&s).ok()
+    }
+    /// `current` is missing, points at a missing version or at one without executables
+    pub fn is_broken(&self, name: &str, bins: &[String]) -> bool {
+        match self.current(name) {
+            Some(tag) => {
+                !self.is_installed(name, &tag)
+                    || self
+                        .executables(name, &tag, bins)
+                        .map_or(true, |exes| exes.iter().all(|exe| !exe.is_file()))
+            }
+            None => true,
+        }
+    }
+    /// The version installed before the current one, the newest one if nothing is current
+    pub fn previous(&self, name: &str) -> io::Result<Option<String>> {
+        let versions = self.installed_versions(name)?;
+        let current = self.current(name);
+        Ok(
+            match versions
+                .iter()
+                .position(|tag| Some(tag) == current.as_ref())
+            {
+                Some(i) => i.checked_sub(1).map(|i| versions[i].clone()),
+                None => versions.last().cloned(),
+            },
+        )
+    }
+
+    /// Download and unpack the release selected by `pkg`, then link its executables.
+    /// Returns the installed tag, an already installed tag is only relinked
+    pub async fn install(
+        &self,
+        name: &str,
+        pkg: &Package,
+        progress: common::Progress,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let src = pkg
+            .repo
+            .parse::<RepoSpec>()
+            .map_err(|e| format!("{}: {}", name, e))?
+            .source()?;
+        let release = pkg
+            .release_finder()?
+            .find(&*src)
+            .await?
+            .ok_or_else(|| format!("{}: no matching release in {}", name, pkg.repo))?;
+        let tag = release.tag_name.clone();
+
+        if !self.is_installed(name, &tag) {
+            let asset = pkg
+                .find_assets(&release.assets)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| format!("{}: no matching asset in {}", name, tag))?;
+            let asset_name = asset.name.clone();
+            let bytes = src.download_asset(&asset, progress).await?;
+
+            // Unpack next to the final location so a failed install leaves nothing behind
+            let dir = self.version_dir(name, &tag);
+            let tmp = self
+                .package_dir(name)
+                .join(format!(".{}.partial", tag.replace('/', "_")));
+            if tmp.exists() {
+                fs::remove_dir_all(&tmp)?;
+            }
+            fs::create_dir_all(&tmp)?;
+            let _partial = Partial(&tmp);
+            unpack(
+                &tmp,
+                &asset_name,
+                &bytes,
+                pkg.bin.first().map(|s| s.as_str()),
+            )?;
+            if let Some(cmd) = &pkg.install {
+                let status = Command::new("sh")
+                    .arg("-c")
+                    .arg(cmd)
+                    .current_dir(&tmp)
+                    .status()?;
+                if !status.success() {
+                    return Err(format!("{}: install command failed with {}", name, status).into());
+                }
+            }
+            fs::rename(&tmp, &dir)?;
+        }
+
+        self.link(name, &tag, &pkg.bin)?;
+        fs::write(
+            self.package_dir(name).join(PACKAGE_FILE),
+            toml::to_string(pkg)?,
+        )?;
+        Ok(tag)
+    }
+
+    /// Remove every installed version and the current version's entries in the bin directory.
+    /// Shims are kept, they still run the executable from a project's own store
+    pub fn uninstall(&self, name: &str, bins: &[String]) -> io::Result<()> {
+        if let (false, Some(tag)) = (self.shims, self.current(name)) {
+            for exe in self.executables(name, &tag, bins).unwrap_or_default() {
+                if let Some(file_name) = exe.file_name() {
+                    let link = self.bin_dir().join(file_name);
+                    if fs::symlink_metadata(&link).is_ok() {
+                        fs::remove_file(link)?;
+                    }
+                }
+            }
+        }
+        fs::remove_dir_all(self.package_dir(name))
+    }
+
+    /// The executables exposed by an installed version, `bins` as in [`Package::bin`]
+    pub fn executables(&self, name: &str, tag: &str, bins: &[String]) -> io::Result<Vec<PathBuf>> {
+        let dir = self.version_dir(name, tag);
+        if bins.is_empty() {
+            executables(&dir)
+        } else {
+            Ok(bins.iter().map(|b| dir.join(b)).collect())
+        }
+    }
+    /// Path of `exe` inside an installed version
+    pub fn find_executable(&self, name: &str, tag: &str, exe: &str) -> Option<PathBuf> {
+        self.executables(name, tag, &[])
+            .ok()?
+            .into_iter()
+            .find(|path| path.file_name().is_some_and(|f| f == exe))
+    }
+
+    /// Point `current` and the bin directory at an installed version
+    pub fn link(&self, name: &str, tag: &str, bins: &[String]) -> io::Result<()> {
+        let bin_dir = self.bin_dir();
+        fs::create_dir_all(&bin_dir)?;
+        for exe in self.executables(name, tag, bins)? {
+            if let Some(file_name) = exe.file_name() {
+                let link = bin_dir.join(file_name);
+                if self.shims {
+                    shim::write(&link, &file_name.to_string_lossy())?;
+                } else {
+                    replace_symlink(&exe, &link)?;
+                }
+            }
+        }
+        replace_symlink(
+            &self.version_dir(name, tag),
+            &self.package_dir(name).join("current"),
+        )
+    }
+}
+
+fn replace_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(link).is_ok() {
+        fs::remove_file(link)?;
+    }
+    symlink(target, link)
+}
+
+/// A version being unpacked, removed again unless it was renamed into place
+struct Partial<'a>(&'a Path);
+
+impl Drop for Partial<'_> {
+    fn drop(&mut self) {
+        if self.0.exists() {
+            let _ = fs::remove_dir_all(self.0);
+        }
+    }
+}
+
+/// Extract archives with the system tools, anything else is assumed to be the executable itself
+fn unpack(dir: &Path, asset_name: &str, bytes: &[u8], bin: Option<&str>) -> io::Result<()> {
+    let format = Format::from_name(asset_name);
+    let is_tar = format.is_some_and(Format::is_tar);
+    let is_zip = format == Some(Format::Zip);
+
+    if !is_tar && !is_zip {
+        let path = dir.join(bin.unwrap_or(asset_name));
+        fs::write(&path, bytes)?;
+        return fs::set_permissions(&path, fs::Permissions::from_mode(0o755));
+    }
+
+    let archive = dir.join(asset_name);
+    fs::write(&archive, bytes)?;
+    let status = if is_zip {
+        Command::new("unzip")
+            .arg("-q")
+            .arg("-o")
+            .arg(&archive)
+            .arg("-d")
+            .arg(dir)
+            .status()?
+    } else {
+        Command::new("tar")
+            .arg("-xf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(dir)
+            .status()?
+    };
+    fs::remove_file(&archive)?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("failed to unpack {}", asset_name)))
+    }
+}
+
+/// Every executable regular file below `dir`
+fn executables(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let ty = entry.file_type()?;
+        if ty.is_dir() {
+            found.extend(executables(&entry.path())?);
+        } else if ty.is_file() && entry.metadata()?.permissions().mode() & 0o111 != 0 {
+            found.push(entry.path());
+        }
+    }
+    Ok(found)
+}
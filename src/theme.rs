@@ -0,0 +1,139 @@
+//! Styles of the TUI, `[tui.theme]` replaces them one by one
+use std::{collections::BTreeMap, env};
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::Widget,
+};
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Labels in front of the text fields
+    pub key: Style,
+    pub field: Style,
+    /// A Release or Asset field that does not compile
+    pub field_invalid: Style,
+    pub button: Style,
+    /// The selected row of the focused table, the focused button
+    pub selected: Style,
+    pub border: Style,
+    /// Behind every pane
+    pub background: Style,
+    /// Drop every colour, `NO_COLOR` is set
+    pub no_color: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            key: Style::default()
+                .fg(Color::White)
+                .bg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            field: Style::default().fg(Color::White).bg(Color::Black),
+            field_invalid: Style::default().fg(Color::Red).bg(Color::Black),
+            button: Style::default()
+                .fg(Color::White)
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            selected: Style::default().add_modifier(Modifier::REVERSED),
+            border: Style::default().fg(Color::White),
+            background: Style::default().bg(Color::Black),
+            no_color: env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+        }
+    }
+}
+
+impl Theme {
+    pub fn from_config(theme: &BTreeMap<String, String>) -> Result<Self, String> {
+        let mut t = Self::default();
+        for (name, style) in theme {
+            let slot = match name.as_str() {
+                "key" => &mut t.key,
+                "field" => &mut t.field,
+                "field_invalid" => &mut t.field_invalid,
+                "button" => &mut t.button,
+                "selected" => &mut t.selected,
+                "border" => &mut t.border,
+                "background" => &mut t.background,
+                _ => return Err(format!("Unknown style {} in [tui.theme]", name)),
+            };
+            *slot = parse_style(style).map_err(|e| format!("{} in [tui.theme]: {}", name, e))?;
+        }
+        Ok(t)
+    }
+}
+
+/// `[FG] [on BG] [MODIFIER...]`, eg. `white on blue bold`, `#ff8800 underlined` or `reversed`
+pub fn parse_style(s: &str) -> Result<Style, String> {
+    let mut style = Style::default();
+    let mut words = s.split_whitespace();
+    while let Some(word) = words.next() {
+        let lower = word.to_lowercase();
+        style = match lower.as_str() {
+            "on" => {
+                let bg = words.next().ok_or("no colour after on")?;
+                style.bg(parse_color(bg).ok_or_else(|| format!("unknown colour {}", bg))?)
+            }
+            "bold" => style.add_modifier(Modifier::BOLD),
+            "dim" => style.add_modifier(Modifier::DIM),
+            "italic" => style.add_modifier(Modifier::ITALIC),
+            "underlined" => style.add_modifier(Modifier::UNDERLINED),
+            "reversed" => style.add_modifier(Modifier::REVERSED),
+            "crossed_out" => style.add_modifier(Modifier::CROSSED_OUT),
+            _ => style.fg(parse_color(word).ok_or_else(|| format!("unknown colour {}", word))?),
+        };
+    }
+    Ok(style)
+}
+
+/// A colour name, `#RRGGBB` or an index into the 256 colour palette
+fn parse_color(s: &str) -> Option<Color> {
+    if let Some(hex) = s.strip_prefix('#') {
+        let n = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)?;
+        return Some(Color::Rgb((n >> 16) as u8, (n >> 8) as u8, n as u8));
+    }
+    if let Ok(i) = s.parse() {
+        return Some(Color::Indexed(i));
+    }
+    Some(
+        match s.to_lowercase().replace(&['_', '-'][..], "").as_str() {
+            "reset" | "default" => Color::Reset,
+            "black" => Color::Black,
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "gray" | "grey" => Color::Gray,
+            "darkgray" | "darkgrey" => Color::DarkGray,
+            "lightred" => Color::LightRed,
+            "lightgreen" => Color::LightGreen,
+            "lightyellow" => Color::LightYellow,
+            "lightblue" => Color::LightBlue,
+            "lightmagenta" => Color::LightMagenta,
+            "lightcyan" => Color::LightCyan,
+            "white" => Color::White,
+            _ => return None,
+        },
+    )
+}
+
+/// Drawn last, resets the colours of everything drawn before it and keeps bold, reversed and the like
+pub struct NoColor;
+
+impl Widget for NoColor {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            }
+        }
+    }
+}
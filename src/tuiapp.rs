@@ -1,78 +1,623 @@
+use chrono::{DateTime, Local, Utc};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event as CEvent, KeyCode, KeyEvent},
+    cursor,
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event as CEvent, EventStream, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use futures::StreamExt;
 use itertools::Itertools;
-use octocrab::models::repos::{Asset, Release};
 use regex::Regex;
+use std::ops::Add;
 use std::{
     convert::TryInto,
-    io::stdout,
-    mem::MaybeUninit,
-    sync::mpsc::{Receiver, Sender},
+    io::{stdout, Write},
+    mem, panic,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
 };
-use std::{ops::Add, sync::mpsc};
 use std::{
     sync::Arc,
     time::{Duration, Instant},
 };
-use std::{sync::Mutex, thread};
-use tokio::runtime::Runtime;
-use tui::widgets::Table;
-use tui::widgets::{Block, BorderType, Borders, Paragraph, Row, Wrap};
+use tokio::{
+    runtime::{Handle, Runtime},
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    sync::Semaphore,
+    task::{self, LocalSet},
+};
+use tui::widgets::{Block, BorderType, Borders, Cell, Clear, Paragraph, Row, Wrap};
+use tui::widgets::{LineGauge, Table, TableState};
 use tui::Frame;
-use tui::{backend::CrosstermBackend, Terminal};
+use tui::{
+    backend::{Backend, CrosstermBackend},
+    Terminal,
+};
 use tui::{
     layout::Direction,
     style::{Color, Modifier, Style},
 };
 use tui::{
     layout::{Alignment, Constraint, Layout, Rect},
-    text::Text,
+    text::{Span, Spans, Text},
+};
+
+use crate::{
+    commands::{self, Changelog, Installable},
+    common::{self, ReleaseFinder},
+    config::{Config, Package, TuiConfig},
+    detect,
+    forge::{self, Asset, Error, GitHub, Release, ReleaseSource, RepoLink, RepoSpec, RepoSummary},
+    keymap::{self, Action, Keymap},
+    lineedit::{self, LineEdit},
+    markdown,
+    state::{RepoFields, State},
+    theme::{NoColor, Theme},
+    Args,
 };
 
-use crate::{common, ArgFlags, Args};
+/// Makes the release source for an owner and repository, [`forge::source`] outside of tests
+type Sources = Rc<dyn Fn(&str, &str) -> Result<Box<dyn ReleaseSource>, Error>>;
 
-type Backend = CrosstermBackend<std::io::Stdout>;
+/// Which part of the screen receives the keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    /// One of the text fields, see `TuiApp::field_selected`
+    Fields,
+    Releases,
+    Assets,
+    /// The button row, see `TuiApp::button_selected`
+    Buttons,
+    /// The jobs panel, see `TuiApp::job_selected`
+    Jobs,
+}
 
+/// The actions in the button row, in order
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Event {
-    Tick,
-    Input(KeyEvent),
+enum Button {
+    Install,
+    Download,
+    Save,
+    Link,
 }
-#[derive(Debug, Clone, PartialEq)]
+
+impl Button {
+    const ALL: [Button; 4] = [
+        Button::Install,
+        Button::Download,
+        Button::Save,
+        Button::Link,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Button::Install => "Install",
+            Button::Download => "Download",
+            Button::Save => "Save",
+            Button::Link => "Link",
+        }
+    }
+    /// Shortcut outside of the text fields
+    fn action(self) -> Action {
+        match self {
+            Button::Install => Action::Install,
+            Button::Download => Action::Download,
+            Button::Save => Action::Save,
+            Button::Link => Action::CopyLink,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Info,
+    Error,
+}
+
+/// A line in the log pane, the newest one is also the status line
+#[derive(Debug, Clone)]
+struct LogLine {
+    at: DateTime<Local>,
+    level: Level,
+    text: String,
+}
+
+/// Lines kept for the log pane
+const LOG_LINES: usize = 200;
+
+/// Releases requested at a time, more pages are loaded while scrolling
+const RELEASE_PAGE: u8 = 30;
+
+/// Repositories shown in the dropdown under the Owner and Repo fields
+const SEARCH_RESULTS: u8 = 8;
+/// Typing pause before the Owner and Repo fields are searched
+const SEARCH_DELAY: Duration = Duration::from_millis(300);
+/// Lookups running at the same time, see `spawn_workers`
+const LOOKUPS: usize = 8;
+
+/// A search result or a remembered repository in the dropdown
+#[derive(Debug, Clone)]
+struct Candidate {
+    summary: RepoSummary,
+    /// The fields a recent or favourite repository was last used with, whether it is a favourite
+    remembered: Option<(bool, RepoFields)>,
+    /// Looked up once the candidate is highlighted
+    latest: Option<String>,
+    latest_requested: bool,
+}
+
+/// Switched with 1, 2 and 3 outside the text fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Search,
+    Installed,
+    /// Starred or watched repositories, see `TuiApp::starred_watched`
+    Starred,
+}
+
+/// A package in the global store
+#[derive(Debug, Clone)]
+struct Installed {
+    name: String,
+    /// The global package file's entry, otherwise the entry it was last installed with
+    pkg: Option<Package>,
+    /// Owner and Repo fields for the package's repository
+    repo: Option<(String, String)>,
+    current: Option<String>,
+    /// `Some(None)` when there is no release or it could not be looked up
+    latest: Option<Option<String>>,
+    latest_requested: bool,
+    /// The global package file pins a tag
+    pinned: bool,
+    broken: bool,
+}
+
+impl Installed {
+    fn bins(&self) -> &[String] {
+        self.pkg.as_ref().map_or(&[], |pkg| &pkg.bin)
+    }
+    fn status(&self) -> (&'static str, Color) {
+        if self.broken {
+            return ("broken", Color::Red);
+        }
+        if self.pinned {
+            return ("pinned", Color::Cyan);
+        }
+        match &self.latest {
+            None if self.latest_requested => ("checking", Color::DarkGray),
+            None | Some(None) => ("unknown", Color::DarkGray),
+            Some(Some(latest)) if Some(latest) == self.current.as_ref() => {
+                ("up to date", Color::Green)
+            }
+            Some(Some(_)) => ("outdated", Color::Yellow),
+        }
+    }
+}
+
+/// Labels in the title of the top bar with the key switching to them, see `TuiApp::tabs`
+const TABS: [(Tab, Action, &str); 3] = [
+    (Tab::Search, Action::SearchTab, "Search"),
+    (Tab::Installed, Action::InstalledTab, "Installed"),
+    (Tab::Starred, Action::StarredTab, "Starred"),
+];
+
+/// Keys for these work anywhere in the search tab, while typing only if they cannot be typed
+const GLOBAL_ACTIONS: [Action; 13] = [
+    Action::Install,
+    Action::Download,
+    Action::Save,
+    Action::CopyLink,
+    Action::ToggleLog,
+    Action::ToggleFuzzy,
+    Action::ScrollDown,
+    Action::ScrollUp,
+    Action::Fullscreen,
+    Action::InstalledTab,
+    Action::StarredTab,
+    Action::Favourite,
+    Action::Changelog,
+];
+
+/// Commands of the palette and what they do, for the help overlay
+const COMMANDS: [(&str, &str); 16] = [
+    ("install", "Install the selected asset"),
+    (
+        "download [PATH]",
+        "Download the selected asset, into . by default",
+    ),
+    (
+        "save [PATH]",
+        "Save the fields as a package, to the global file by default",
+    ),
+    ("link", "Copy the link of the selected asset"),
+    (
+        "repo OWNER/REPO",
+        "List the releases of a repository, or of a link",
+    ),
+    ("tag TAG", "Select the release tagged TAG"),
+    ("asset FILTER", "Filter the assets"),
+    ("fuzzy", "Switch between fuzzy and regex filters"),
+    (
+        "favourite",
+        "Add the repository to the favourites or remove it",
+    ),
+    (
+        "changelog",
+        "Release notes since the installed version up to the selected release",
+    ),
+    ("installed", "Show the installed packages"),
+    (
+        "starred",
+        "List the starred repositories that can be installed here",
+    ),
+    (
+        "watched",
+        "List the watched repositories that can be installed here",
+    ),
+    ("search", "Show the search tab"),
+    ("help", "Show this help"),
+    ("quit", "Quit"),
+];
+
+type JobId = usize;
+
+/// Travels with an install or download to the job worker
+#[derive(Debug, Clone)]
+struct JobHandle {
+    id: JobId,
+    cancel: Arc<AtomicBool>,
+}
+
+impl JobHandle {
+    fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+    /// Forwards the transfer progress to the TUI and aborts it once the job is cancelled
+    fn progress(&self, tx: UnboundedSender<Downloaded>) -> common::Progress {
+        let (job, cancel) = (self.id, self.cancel.clone());
+        let mut last = None;
+        Box::new(move |done, total| {
+            if last != Some(done) {
+                last = Some(done);
+                let _ = tx.send(Downloaded::Progress { job, done, total });
+            }
+            !cancel.load(Ordering::Relaxed)
+        })
+    }
+    fn report(&self, result: Result<String, String>) -> Downloaded {
+        match result {
+            Ok(message) => Downloaded::Finished {
+                job: self.id,
+                message,
+            },
+            Err(error) => Downloaded::Failed {
+                job: self.id,
+                error,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 enum DownloadPlease {
-    Releases(String, String),
-    Asset(Asset),
+    ReleasePage {
+        owner: String,
+        repo: String,
+        page: u32,
+    },
+    SearchRepos(String),
+    LatestRelease {
+        owner: String,
+        repo: String,
+    },
+    Starred {
+        watched: bool,
+    },
+    /// Release notes of `repo` after `from` up to `to`, the latest release by default
+    Changelog {
+        repo: String,
+        from: String,
+        to: Option<String>,
+    },
+    Install(JobHandle, String, Package),
+    /// An asset of the repository `OWNER/REPO` to a path
+    Download(JobHandle, String, Asset, PathBuf),
+}
+
+/// Everything the workers report back
+#[derive(Debug, Clone, PartialEq)]
+enum Downloaded {
+    ReleasePage {
+        owner: String,
+        repo: String,
+        page: u32,
+        releases: Result<Vec<Release>, String>,
+    },
+    Repos {
+        query: String,
+        repos: Result<Vec<RepoSummary>, String>,
+    },
+    LatestRelease {
+        owner: String,
+        repo: String,
+        tag: Result<Option<String>, String>,
+    },
+    Starred {
+        watched: bool,
+        repos: Result<Vec<Installable>, String>,
+    },
+    Changelog(Result<Changelog, String>),
+    Started(JobId),
+    Progress {
+        job: JobId,
+        done: u64,
+        total: Option<u64>,
+    },
+    /// With a message for the status line
+    Finished {
+        job: JobId,
+        message: String,
+    },
+    Failed {
+        job: JobId,
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum JobState {
+    Queued,
+    Running,
+    Finished(String),
+    Failed(String),
+    Cancelled,
+}
+
+/// An install or download in the jobs panel
+#[derive(Debug)]
+struct Job {
+    handle: JobHandle,
+    title: String,
+    state: JobState,
+    done: u64,
+    total: Option<u64>,
+    started: Option<Instant>,
+}
+
+impl Job {
+    fn ratio(&self) -> f64 {
+        match (&self.state, self.total) {
+            (JobState::Finished(_), _) => 1.0,
+            (_, Some(total)) if total > 0 => (self.done as f64 / total as f64).min(1.0),
+            _ => 0.0,
+        }
+    }
+    /// Bytes per second since the job started
+    fn speed(&self) -> Option<f64> {
+        let secs = self.started?.elapsed().as_secs_f64();
+        if secs > 0.0 {
+            Some(self.done as f64 / secs)
+        } else {
+            None
+        }
+    }
+    fn label(&self) -> String {
+        let state = match &self.state {
+            JobState::Queued => String::from("queued"),
+            JobState::Running => {
+                let size = match self.total {
+                    Some(total) => format!("{}/{}", human_bytes(self.done), human_bytes(total)),
+                    None => human_bytes(self.done),
+                };
+                match self.speed() {
+                    Some(speed) => format!("{} {}/s", size, human_bytes(speed as u64)),
+                    None => size,
+                }
+            }
+            JobState::Finished(_) => String::from("done"),
+            JobState::Failed(e) => format!("failed: {}", e),
+            JobState::Cancelled => String::from("cancelled"),
+        };
+        format!("{} [{}] ", self.title, state)
+    }
+}
+
+/// The Description of an asset, `siblings` are the assets of its release
+fn asset_details(ass: &Asset, siblings: &[Asset]) -> Text<'static> {
+    let time = |t: Option<DateTime<Utc>>| {
+        t.map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_else(|| String::from("unknown"))
+    };
+    let names = |assets: Vec<&Asset>| {
+        if assets.is_empty() {
+            String::from("none")
+        } else {
+            assets.iter().map(|a| a.name.as_str()).join(", ")
+        }
+    };
+    let platform = detect::Platform::from_name(&ass.name);
+    let rows = [
+        ("Name", ass.name.clone()),
+        ("Label", ass.label.clone().unwrap_or_default()),
+        (
+            "Size",
+            ass.size
+                .map(human_bytes)
+                .unwrap_or_else(|| String::from("unknown")),
+        ),
+        ("Type", ass.content_type.clone().unwrap_or_default()),
+        (
+            "Format",
+            detect::Format::from_name(&ass.name)
+                .map(|f| f.to_string())
+                .unwrap_or_else(|| String::from("executable or unknown")),
+        ),
+        (
+            "Platform",
+            if platform.is_unknown() {
+                String::from("unknown")
+            } else {
+                platform.to_string()
+            },
+        ),
+        (
+            "Downloads",
+            ass.download_count
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+        ),
+        ("Uploader", ass.uploader.clone().unwrap_or_default()),
+        ("Created", time(ass.created_at)),
+        ("Updated", time(ass.updated_at)),
+        ("Checksum", names(detect::checksums(ass, siblings))),
+        ("Signature", names(detect::signatures(ass, siblings))),
+        ("URL", ass.browser_download_url.to_string()),
+    ];
+    let key = Style::default().add_modifier(Modifier::BOLD);
+    let lines = rows
+        .iter()
+        .map(|(name, value)| {
+            Spans::from(vec![
+                Span::styled(format!("{:<10}", name), key),
+                Span::raw(value.clone()),
+            ])
+        })
+        .collect_vec();
+    Text::from(lines)
+}
+
+fn human_bytes(n: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = n as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", n)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
 }
 
 #[derive(Debug)]
 struct TuiApp {
-    args: ArgFlags,
+    tab: Tab,
+    keymap: Keymap,
+    theme: Theme,
 
     desc_box_size: u16,
+    /// Lines scrolled off the top of the description
+    desc_scroll: u16,
+    /// Lines visible in the description at the last draw, for PageUp/PageDown
+    desc_rows: u16,
+    /// The description takes the whole screen
+    desc_fullscreen: bool,
+    /// The rendered description and what it describes, so Markdown is not parsed every frame
+    desc_cache: Option<(String, Text<'static>)>,
+    /// Shown in the description instead of the release until it is closed, with its title
+    changelog: Option<(String, Text<'static>)>,
 
     field_selected: usize,
-    owner: String,
-    repo: String,
-    search_rels: String,
-    search_assets: String,
+    /// Keys are typed into the selected field rather than taken as actions
+    insert: bool,
+    owner: LineEdit,
+    repo: LineEdit,
+    search_rels: LineEdit,
+    search_assets: LineEdit,
 
+    /// The last regexes that compiled, the fields keep filtering with these while they are invalid
     release_re: Option<Regex>,
     asset_re: Option<Regex>,
+    release_re_error: Option<String>,
+    asset_re_error: Option<String>,
 
-    selected_col: usize,
+    focus: Focus,
     selected_asset: usize,
     selected_release: usize,
+    release_table: TableState,
+    asset_table: TableState,
+    /// First visible row of each table, `TableState` keeps its own private, see `table_offset`
+    release_offset: usize,
+    asset_offset: usize,
+    /// Rows visible in the tables at the last draw, for PageUp/PageDown
+    table_rows: usize,
     all_releases: Vec<Release>,
     found_releases: Vec<Release>,
+    /// Indices of the characters the filter matched in each tag, only for fuzzy filtering
+    release_hits: Vec<Vec<usize>>,
+    /// Owner and repo the loaded releases belong to, pages for anything else are stale
+    listed_repo: (String, String),
+    /// The next page of releases to request, `None` once every page is loaded
+    next_page: Option<u32>,
+    /// A page of releases was requested and has not arrived yet
+    loading_releases: bool,
+
+    repo_candidates: Vec<Candidate>,
+    candidate_selected: Option<usize>,
+    /// When to search for what was typed into the Owner and Repo fields, pushed back by each key
+    search_due: Option<Instant>,
+    /// The last query sent, results for anything else are stale
+    search_sent: String,
     found_assets: Vec<Asset>,
+    asset_hits: Vec<Vec<usize>>,
+    /// Filter releases and assets by fuzzy matching rather than regex
+    fuzzy: bool,
+
+    button_selected: usize,
+    /// Path typed so far while asking where to download to
+    download_prompt: Option<String>,
+    /// Command typed so far into the palette
+    command_prompt: Option<String>,
+    /// Lines scrolled off the top of the help overlay, `None` while it is closed
+    help_scroll: Option<usize>,
+    /// Set by the `quit` command
+    quit: bool,
+    log: Vec<LogLine>,
+    /// Show the log in place of the description
+    show_log: bool,
+    /// Printed after the terminal is restored, eg. links when there is no clipboard
+    print_on_exit: Vec<String>,
 
-    try_dl_repo: Sender<DownloadPlease>,
-    get_dl_repo: Receiver<Vec<Release>>,
+    jobs: Vec<Job>,
+    job_selected: usize,
+    next_job: JobId,
+
+    installed: Vec<Installed>,
+    installed_selected: usize,
+    installed_table: TableState,
+    installed_offset: usize,
+    /// Package waiting for a second `x` to be uninstalled
+    uninstall_prompt: Option<String>,
+
+    starred: Vec<Installable>,
+    /// The starred tab lists watched repositories instead
+    starred_watched: bool,
+    starred_loading: bool,
+    starred_loaded: bool,
+    starred_selected: usize,
+    starred_table: TableState,
+    starred_offset: usize,
+    starred_area: Rect,
+
+    /// Recent and favourite repositories, saved on exit
+    state: State,
+
+    /// Where everything was at the last draw, for the mouse
+    areas: Option<Areas>,
+    installed_area: Rect,
+
+    try_dl_repo: UnboundedSender<DownloadPlease>,
 }
 
+#[derive(Debug, Clone)]
 struct Areas {
     top_area: Rect,
 
@@ -89,21 +634,28 @@ struct Areas {
     found_assets: Rect,
     found_releases: Rect,
     description: Rect,
+    status: Rect,
 
     buttons: Vec<Rect>,
+    /// Empty while there are no jobs
+    jobs: Rect,
+    /// Below the top bar, only drawn while there are search results
+    dropdown: Rect,
 }
 
+/// Names of the fields in the state file, in the order of `TuiApp::field_selected`
+const FIELD_NAMES: [&str; 4] = ["owner", "repo", "release", "asset"];
+
+/// Jobs shown at once, the panel scrolls to the selected one
+const JOB_ROWS: usize = 5;
+
 fn evensplit<const N: usize>() -> [Constraint; N] {
-    let mut arr = MaybeUninit::uninit_array();
-    for i in 0..N {
-        arr[i] = MaybeUninit::new(Constraint::Ratio(1, N.try_into().unwrap()));
-    }
-    unsafe { MaybeUninit::array_assume_init(arr) }
+    [Constraint::Ratio(1, N.try_into().unwrap()); N]
 }
 
 impl Areas {
     fn new(total: Rect, app: &TuiApp) -> Self {
-        let block = TuiApp::block();
+        let block = app.block();
 
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -111,10 +663,14 @@ impl Areas {
                 Constraint::Length(2 + 2),
                 Constraint::Min(0),
                 Constraint::Length(1),
+                Constraint::Length(app.jobs_height()),
                 Constraint::Length(2 + app.desc_box_size),
+                Constraint::Length(1),
             ])
             .split(total);
-        let (topbar, body, buttons, bottom) = (chunks[0], chunks[1], chunks[2], chunks[3]);
+        let (topbar, body, buttons, jobs, bottom, status) = (
+            chunks[0], chunks[1], chunks[2], chunks[3], chunks[4], chunks[5],
+        );
 
         let body = Layout::default()
             .direction(Direction::Horizontal)
@@ -137,6 +693,14 @@ impl Areas {
             .constraints(evensplit::<4>());
         let toprow = [split.split(toprow1), split.split(toprow2)];
 
+        let below_top = topbar.y + topbar.height;
+        let dropdown = Rect {
+            y: below_top,
+            height: (app.repo_candidates.len() as u16 + 3)
+                .min(total.height.saturating_sub(below_top)),
+            ..topbar
+        };
+
         Self {
             top_area: topbar,
             owner_key: toprow[0][0],
@@ -150,13 +714,16 @@ impl Areas {
             found_releases: body[0],
             found_assets: body[1],
             buttons,
+            jobs,
             description: bottom,
+            status,
+            dropdown,
         }
     }
 }
 
 impl TuiApp {
-    fn selected_field(&self) -> &str {
+    fn selected_field(&self) -> &LineEdit {
         match self.field_selected {
             0 => &self.owner,
             1 => &self.repo,
@@ -165,8 +732,11 @@ impl TuiApp {
             _ => panic!("Invalid field"),
         }
     }
-    fn selected_field_mut(&mut self) -> &mut String {
-        match self.field_selected {
+    fn selected_field_mut(&mut self) -> &mut LineEdit {
+        self.field_mut(self.field_selected)
+    }
+    fn field_mut(&mut self, i: usize) -> &mut LineEdit {
+        match i {
             0 => &mut self.owner,
             1 => &mut self.repo,
             2 => &mut self.search_rels,
@@ -174,32 +744,142 @@ impl TuiApp {
             _ => panic!("Invalid field"),
         }
     }
-    fn new(
+    /// With the `[tui]` config section and the state of the last run
+    fn new(args: Args, try_dl_repo: UnboundedSender<DownloadPlease>) -> Self {
+        let mut load_errors = Vec::new();
+        let config = Config::load_global().map(|c| c.tui).unwrap_or_else(|e| {
+            load_errors.push(e.to_string());
+            TuiConfig::default()
+        });
+        let state = State::load().unwrap_or_else(|e| {
+            load_errors.push(e.to_string());
+            State::default()
+        });
+        let mut app = Self::with(args, try_dl_repo, &config, state);
+        for e in load_errors {
+            app.error(e);
+        }
+        app
+    }
+
+    fn with(
         args: Args,
-        try_dl_repo: Sender<DownloadPlease>,
-        get_dl_repo: Receiver<Vec<Release>>,
+        try_dl_repo: UnboundedSender<DownloadPlease>,
+        config: &TuiConfig,
+        state: State,
     ) -> Self {
-        let app = Self {
-            owner: args.owner.clone().unwrap_or(String::from(".*")),
-            repo: args.repo.clone().unwrap_or(String::from("*")),
-            search_rels: args.release.clone().unwrap_or(String::from("*")),
-            search_assets: args.asset.clone().unwrap_or(String::from("*")),
+        let mut config_errors = Vec::new();
+        let keymap = Keymap::from_config(config).unwrap_or_else(|e| {
+            config_errors.push(e);
+            Keymap::default()
+        });
+        let theme = Theme::from_config(&config.theme).unwrap_or_else(|e| {
+            config_errors.push(e);
+            Theme::default()
+        });
+        let mut app = Self {
+            owner: LineEdit::new(args.owner.clone().unwrap_or_default()),
+            repo: LineEdit::new(args.repo.clone().unwrap_or_default()),
+            search_rels: LineEdit::new(
+                args.release
+                    .as_deref()
+                    .map(release_field_from_selector)
+                    .unwrap_or_default(),
+            ),
+            search_assets: LineEdit::new(
+                args.asset
+                    .as_deref()
+                    .map(|a| a.strip_prefix("f:").unwrap_or(a))
+                    .unwrap_or_default(),
+            ),
+            fuzzy: [&args.release, &args.asset]
+                .iter()
+                .any(|sel| sel.as_deref().is_some_and(|s| s.starts_with("f:"))),
             desc_box_size: 10,
+            desc_scroll: 0,
+            desc_rows: 10,
+            desc_fullscreen: false,
+            desc_cache: None,
+            changelog: None,
             field_selected: 0,
+            insert: keymap.insert_on_focus,
+            keymap,
+            theme,
             found_releases: Vec::new(),
+            release_hits: Vec::new(),
             all_releases: Vec::new(),
+            listed_repo: (String::new(), String::new()),
+            next_page: None,
+            loading_releases: false,
+            repo_candidates: Vec::new(),
+            candidate_selected: None,
+            search_due: None,
+            search_sent: String::new(),
             found_assets: Vec::new(),
-            selected_col: 0,
+            asset_hits: Vec::new(),
+            focus: Focus::Fields,
             selected_release: 0,
             selected_asset: 0,
+            release_table: TableState::default(),
+            asset_table: TableState::default(),
+            release_offset: 0,
+            asset_offset: 0,
+            table_rows: 10,
+            button_selected: 0,
+            download_prompt: None,
+            command_prompt: None,
+            help_scroll: None,
+            quit: false,
+            log: Vec::new(),
+            show_log: false,
+            print_on_exit: Vec::new(),
+            jobs: Vec::new(),
+            job_selected: 0,
+            next_job: 0,
+            tab: Tab::Search,
+            installed: Vec::new(),
+            installed_selected: 0,
+            installed_table: TableState::default(),
+            installed_offset: 0,
+            areas: None,
+            installed_area: Rect::default(),
+            uninstall_prompt: None,
+            starred: Vec::new(),
+            starred_watched: false,
+            starred_loading: false,
+            starred_loaded: false,
+            starred_selected: 0,
+            starred_table: TableState::default(),
+            starred_offset: 0,
+            starred_area: Rect::default(),
+            state,
             release_re: None,
             asset_re: None,
-            args: args.flags,
+            release_re_error: None,
+            asset_re_error: None,
             try_dl_repo,
-            get_dl_repo,
         };
+        for e in config_errors {
+            app.error(e);
+        }
+        for (i, name) in FIELD_NAMES.iter().enumerate() {
+            let history = app.state.history.get(*name).cloned().unwrap_or_default();
+            app.field_mut(i).set_history(history);
+        }
+        let no_args = [&args.owner, &args.repo, &args.release, &args.asset]
+            .iter()
+            .all(|arg| arg.is_none());
+        if let (true, Some(last)) = (no_args, app.state.last().cloned()) {
+            if let Err(e) = app.restore(last) {
+                // A stale state file shouldn't keep the TUI from starting
+                app.error(format!("Could not restore the last search: {}", e));
+                app.restore_failed();
+            }
+        }
         if let (Some(_), Some(_)) = (args.owner, args.repo) {
-            app.update_release_list().unwrap();
+            if let Err(e) = app.update_release_list() {
+                app.error(e.to_string());
+            }
             // if let Some(_) = args.release {
             //     app.update_release_re(true).unwrap();
             // }
@@ -210,75 +890,234 @@ impl TuiApp {
         app
     }
 
-    fn draw(&self, f: &mut Frame<Backend>) {
+    fn draw(&mut self, f: &mut Frame<impl Backend>) {
+        self.draw_screen(f);
+        if self.help_scroll.is_some() {
+            self.draw_help(f);
+        }
+        if self.theme.no_color {
+            f.render_widget(NoColor, f.size());
+        }
+    }
+
+    /// The key bindings and the palette commands over everything else
+    fn draw_help(&mut self, f: &mut Frame<impl Backend>) {
+        let keys = Style::default().add_modifier(Modifier::BOLD);
+        let heading = |text| {
+            Row::new(vec![Span::styled(
+                text,
+                keys.add_modifier(Modifier::UNDERLINED),
+            )])
+        };
+        let mut rows = vec![heading(String::from("Keys"))];
+        rows.extend(self.keymap.bindings().into_iter().map(|(k, help, name)| {
+            Row::new(vec![
+                Cell::from(Span::styled(k, keys)),
+                Cell::from(help),
+                Cell::from(Span::styled(name, Style::default().fg(Color::DarkGray))),
+            ])
+        }));
+        rows.push(Row::new(vec![""]));
+        rows.push(heading(String::from("Typing into a field")));
+        rows.extend(
+            lineedit::KEYS.iter().map(|&(k, help)| {
+                Row::new(vec![Cell::from(Span::styled(k, keys)), Cell::from(help)])
+            }),
+        );
+        rows.push(Row::new(vec![""]));
+        rows.push(heading(format!(
+            "Commands [{}]",
+            self.keymap.keys(Action::Command)
+        )));
+        rows.extend(COMMANDS.iter().map(|&(cmd, help)| {
+            Row::new(vec![Cell::from(Span::styled(cmd, keys)), Cell::from(help)])
+        }));
+
+        let size = f.size();
+        let width = size.width.min(90);
+        let height = size.height.min(rows.len() as u16 + 2);
+        let area = Rect {
+            x: (size.width - width) / 2,
+            y: (size.height - height) / 2,
+            width,
+            height,
+        };
+        // Keep the last row on the screen
+        let visible = height.saturating_sub(2) as usize;
+        let scroll = self
+            .help_scroll
+            .unwrap_or(0)
+            .min(rows.len().saturating_sub(visible));
+        self.help_scroll = Some(scroll);
+        let table = Table::new(rows.into_iter().skip(scroll).collect_vec())
+            .widths(&[
+                Constraint::Length(20),
+                Constraint::Min(10),
+                Constraint::Length(14),
+            ])
+            .block(self.block().title(format!(
+                "Help [{}/{} scroll, any other key closes]",
+                self.keymap.keys(Action::Down),
+                self.keymap.keys(Action::Up)
+            )));
+        f.render_widget(Clear, area);
+        f.render_widget(table, area);
+    }
+
+    fn on_help_key(&mut self, key: KeyEvent) {
+        let actions = [Action::Down, Action::Up, Action::PageDown, Action::PageUp];
+        let scroll = self.help_scroll.unwrap_or(0);
+        self.help_scroll = match self.keymap.action(key, &actions) {
+            Some(Action::Down) => Some(scroll + 1),
+            Some(Action::Up) => Some(scroll.saturating_sub(1)),
+            Some(Action::PageDown) => Some(scroll + 10),
+            Some(Action::PageUp) => Some(scroll.saturating_sub(10)),
+            _ => None,
+        };
+    }
+
+    fn draw_screen(&mut self, f: &mut Frame<impl Backend>) {
+        if self.desc_fullscreen {
+            let size = f.size();
+            let desc = Rect {
+                height: size.height.saturating_sub(1),
+                ..size
+            };
+            self.draw_description(f, self.block(), desc);
+            let status = Rect {
+                y: desc.y + desc.height,
+                height: size.height - desc.height,
+                ..size
+            };
+            self.draw_status(f, status);
+            return;
+        }
+        if self.tab != Tab::Search {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Min(0),
+                    Constraint::Length(self.jobs_height()),
+                    Constraint::Length(1),
+                ])
+                .split(f.size());
+            if self.tab == Tab::Installed {
+                self.draw_installed(f, self.block(), chunks[0]);
+            } else {
+                self.draw_starred(f, self.block(), chunks[0]);
+            }
+            if !self.jobs.is_empty() {
+                self.draw_jobs(f, self.block().title("Jobs"), chunks[1]);
+            }
+            self.draw_status(f, chunks[2]);
+            return;
+        }
         let chunks = Areas::new(f.size(), self);
+        self.areas = Some(chunks.clone());
+        // Borders and the header row
+        self.table_rows = chunks.found_releases.height.saturating_sub(3).max(1) as usize;
+        self.release_offset =
+            table_offset(self.release_offset, self.selected_release, self.table_rows);
+        self.asset_offset = table_offset(self.asset_offset, self.selected_asset, self.table_rows);
 
-        let block = Self::block();
-        f.render_widget(block.clone(), chunks.top_area);
+        let block = self.block();
+        f.render_widget(block.clone().title(self.tabs_title()), chunks.top_area);
 
         let text = |t, s| Paragraph::new(Text::styled(t, s));
 
-        let key_style = Style::default()
-            .fg(Color::White)
-            .bg(Color::Black)
-            .add_modifier(Modifier::BOLD);
-        f.render_widget(text("Owner", key_style), chunks.owner_key);
-        f.render_widget(text("Repo", key_style), chunks.repo_key);
-        f.render_widget(text("Release", key_style), chunks.release_key);
-        f.render_widget(text("Asset", key_style), chunks.asset_key);
+        let focused = |i| self.focus == Focus::Fields && self.field_selected == i;
+        // The label of the field being typed into stands out
+        let key_style = |i| {
+            if focused(i) && self.insert {
+                self.theme.key.add_modifier(Modifier::REVERSED)
+            } else {
+                self.theme.key
+            }
+        };
+        f.render_widget(text("Owner", key_style(0)), chunks.owner_key);
+        f.render_widget(text("Repo", key_style(1)), chunks.repo_key);
+        let (release_key, asset_key) = if self.fuzzy {
+            ("Release (fuzzy)", "Asset (fuzzy)")
+        } else {
+            ("Release", "Asset")
+        };
+        f.render_widget(text(release_key, key_style(2)), chunks.release_key);
+        f.render_widget(text(asset_key, key_style(3)), chunks.asset_key);
 
         let field_style = |i| {
-            let a = Style::default().fg(Color::White).bg(Color::Black);
-            if self.field_selected == i {
+            let invalid = match i {
+                2 => self.release_re_error.is_some(),
+                3 => self.asset_re_error.is_some(),
+                _ => false,
+            };
+            let a = if invalid {
+                self.theme.field_invalid
+            } else {
+                self.theme.field
+            };
+            if focused(i) {
                 a.add_modifier(Modifier::UNDERLINED)
             } else {
                 a
             }
         };
-        f.render_widget(text(&self.owner, field_style(0)), chunks.owner_field);
-        f.render_widget(text(&self.repo, field_style(1)), chunks.repo_field);
-        f.render_widget(
-            text(&self.search_rels, field_style(2)),
-            chunks.release_field,
-        );
-        f.render_widget(
-            text(&self.search_assets, field_style(3)),
-            chunks.asset_field,
-        );
+        let fields = [
+            (&self.owner, chunks.owner_field),
+            (&self.repo, chunks.repo_field),
+            (&self.search_rels, chunks.release_field),
+            (&self.search_assets, chunks.asset_field),
+        ];
+        for (i, (field, area)) in fields.iter().enumerate() {
+            // The end with the cursor when the text is too long
+            let (visible, cursor) = field.view(area.width);
+            f.render_widget(text(visible, field_style(i)), *area);
+            let prompt = self.download_prompt.is_some() || self.command_prompt.is_some();
+            if i == self.field_selected && self.typing() && !prompt && self.help_scroll.is_none() {
+                f.set_cursor(area.x + cursor, area.y);
+            }
+        }
 
-        let button_style = Style::default()
-            .fg(Color::White)
-            .bg(Color::Blue)
-            .add_modifier(Modifier::BOLD);
-        f.render_widget(
-            text("Install", button_style).alignment(Alignment::Center),
-            chunks.buttons[0],
-        );
-        f.render_widget(
-            text("Download", button_style).alignment(Alignment::Center),
-            chunks.buttons[1],
-        );
-        f.render_widget(
-            text("Save", button_style).alignment(Alignment::Center),
-            chunks.buttons[2],
-        );
-        f.render_widget(
-            text("Link", button_style).alignment(Alignment::Center),
-            chunks.buttons[3],
-        );
+        let button_style = self.theme.button;
+        if let Some(path) = &self.download_prompt {
+            // The prompt takes over the whole button row
+            let area = chunks
+                .buttons
+                .iter()
+                .fold(chunks.buttons[0], |a, b| a.union(*b));
+            f.render_widget(
+                text(&format!("Download to: {}", path), field_style(0)),
+                area,
+            );
+        } else {
+            for (i, (button, area)) in Button::ALL.iter().zip(&chunks.buttons).enumerate() {
+                let style = if self.focus == Focus::Buttons && self.button_selected == i {
+                    button_style.patch(self.theme.selected)
+                } else {
+                    button_style
+                };
+                let label = format!("{} [{}]", button.label(), self.keymap.keys(button.action()));
+                let button = Paragraph::new(Text::styled(label, style));
+                f.render_widget(button.alignment(Alignment::Center), *area);
+            }
+        }
 
-        // TODO: do pagination (and integrate with the downloader to reduce the release fetch time)
+        let loading = Row::new(vec!["loading more..."]).style(Style::default().fg(Color::DarkGray));
         let releases = Table::new(
             self.found_releases
                 .iter()
-                // .take(10)
-                .map(|rel| {
+                .zip(&self.release_hits)
+                .map(|(rel, hits)| {
                     Row::new(vec![
-                        rel.tag_name.clone(),
-                        rel.published_at.to_string(),
-                        rel.name.clone().unwrap_or(String::from("N/A")),
+                        Cell::from(highlight_hits(&rel.tag_name, hits)),
+                        Cell::from(
+                            rel.published_at
+                                .map(|t| t.to_string())
+                                .unwrap_or(String::from("N/A")),
+                        ),
+                        Cell::from(rel.name.clone().unwrap_or(String::from("N/A"))),
                     ])
                 })
+                .chain(self.loading_releases.then_some(loading))
                 .collect_vec(),
         )
         .widths(&[
@@ -287,20 +1126,28 @@ impl TuiApp {
             Constraint::Max(10),
         ])
         .header(Row::new(vec!["tag_name", "published_at", "name"]));
-        f.render_widget(
-            releases.block(block.clone().title("Releases")),
+        self.release_table
+            .select(if self.found_releases.is_empty() {
+                None
+            } else {
+                Some(self.selected_release)
+            });
+        f.render_stateful_widget(
+            self.highlighted(releases, Focus::Releases)
+                .block(block.clone().title("Releases")),
             chunks.found_releases,
+            &mut self.release_table,
         );
 
         let assets = Table::new(
             self.found_assets
                 .iter()
-                // .take(10)
-                .map(|ass| {
+                .zip(&self.asset_hits)
+                .map(|(ass, hits)| {
                     Row::new(vec![
-                        ass.name.clone(),
-                        ass.label.clone().unwrap_or(String::from("N/A")),
-                        ass.id.to_string(),
+                        Cell::from(highlight_hits(&ass.name, hits)),
+                        Cell::from(ass.label.clone().unwrap_or(String::from("N/A"))),
+                        Cell::from(ass.id.to_string()),
                     ])
                 })
                 .collect_vec(),
@@ -311,276 +1158,2213 @@ impl TuiApp {
             Constraint::Min(10),
         ])
         .header(Row::new(vec!["name", "label", "id"]));
-        f.render_widget(
-            assets.block(block.clone().title("Assets")),
+        self.asset_table.select(if self.found_assets.is_empty() {
+            None
+        } else {
+            Some(self.selected_asset)
+        });
+        f.render_stateful_widget(
+            self.highlighted(assets, Focus::Assets)
+                .block(block.clone().title("Assets")),
             chunks.found_assets,
+            &mut self.asset_table,
         );
 
-        // TODO: format the whole description
-        let desc = match self.selected_col {
-            0 => {
-                //Releases
-                let body = self
-                    .found_releases
-                    .get(self.selected_release)
-                    .and_then(|x| x.body.as_ref())
-                    .map(|x| x.as_str())
-                    .unwrap_or("");
-                Text::raw(body)
-            }
-            1 => {
-                //Assets
-                let body = self.found_releases[self.selected_release].assets[self.selected_asset]
-                    .browser_download_url
-                    .to_string();
-                Text::raw(body)
-            }
-            _ => Text::raw(""),
-        };
-        let desc = Paragraph::new(desc).wrap(Wrap { trim: false });
-        f.render_widget(desc.block(block.title("Description")), chunks.description);
-    }
+        if self.show_log {
+            let title = format!("Log [{}]", self.keymap.keys(Action::ToggleLog));
+            self.draw_log(f, block.clone().title(title), chunks.description);
+        } else {
+            self.draw_description(f, block.clone(), chunks.description);
+        }
+        self.draw_status(f, chunks.status);
 
-    fn update_release_list(&self) -> Result<(), Box<dyn std::error::Error>> {
-        Ok(self.try_dl_repo.send(DownloadPlease::Releases(
-            self.owner.clone(),
-            self.repo.clone(),
-        ))?)
+        if !self.jobs.is_empty() {
+            self.draw_jobs(f, block.clone().title("Jobs"), chunks.jobs);
+        }
+        if self.dropdown_open() {
+            self.draw_dropdown(f, block.title("Repositories"), chunks.dropdown);
+        }
     }
-    fn update_release_re(&mut self, recompile: bool) -> Result<(), Box<dyn std::error::Error>> {
-        if recompile {
-            if self.search_rels == "" {
-                self.release_re = Some(Regex::new(".*")?);
+
+    /// Both tabs with the current one highlighted
+    fn tabs_title(&self) -> Spans<'static> {
+        let tabs = self.tabs().into_iter().map(|(tab, label)| {
+            let style = if self.tab == tab {
+                self.theme.selected
             } else {
-                self.release_re = Some(Regex::new(&self.search_rels)?);
-            }
+                Style::default().fg(Color::DarkGray)
+            };
+            Span::styled(label, style)
+        });
+        Spans::from(Itertools::intersperse(tabs, Span::raw(" ")).collect_vec())
+    }
+    /// The tabs with their labels, eg. `[1] Search`
+    fn tabs(&self) -> Vec<(Tab, String)> {
+        TABS.iter()
+            .map(|&(tab, action, label)| {
+                let label = match tab {
+                    Tab::Starred if self.starred_watched => "Watched",
+                    _ => label,
+                };
+                (tab, format!("[{}] {}", self.keymap.keys(action), label))
+            })
+            .collect()
+    }
+    fn switch_tab(&mut self, tab: Tab) -> Result<(), Box<dyn std::error::Error>> {
+        match tab {
+            Tab::Search => self.tab = Tab::Search,
+            Tab::Installed => self.show_installed()?,
+            Tab::Starred => self.show_starred()?,
         }
-        // eprintln!("{:?}", self.all_releases);
-        self.found_releases = if let Some(re) = &self.release_re {
-            common::find_release_from(&re, &self.all_releases)
-        } else {
-            self.all_releases.clone()
-        };
-        self.selected_release = 0;
-        self.selected_asset = 0;
-        self.update_asset_re(false)?;
         Ok(())
     }
-    fn update_asset_re(&mut self, recompile: bool) -> Result<(), Box<dyn std::error::Error>> {
-        if recompile {
-            if self.search_assets == "" {
-                self.asset_re = Some(Regex::new(".*")?);
-            } else {
-                self.asset_re = Some(Regex::new(&self.search_assets)?);
-            }
+
+    fn draw_installed(&mut self, f: &mut Frame<impl Backend>, block: Block, area: Rect) {
+        let rows = self.installed.iter().map(|row| {
+            let (status, color) = row.status();
+            Row::new(vec![
+                Cell::from(row.name.clone()),
+                Cell::from(row.current.clone().unwrap_or_default()),
+                Cell::from(row.latest.clone().flatten().unwrap_or_default()),
+                Cell::from(Span::styled(status, Style::default().fg(color))),
+                Cell::from(row.pkg.as_ref().map_or("", |pkg| &pkg.repo).to_owned()),
+            ])
+        });
+        let table = Table::new(rows.collect_vec())
+            .widths(&[
+                Constraint::Percentage(20),
+                Constraint::Percentage(15),
+                Constraint::Percentage(15),
+                Constraint::Length(10),
+                Constraint::Min(10),
+            ])
+            .header(Row::new(vec![
+                "name",
+                "installed",
+                "latest",
+                "status",
+                "repository",
+            ]))
+            .highlight_style(self.theme.selected)
+            .highlight_symbol("> ");
+        let mut title = self.tabs_title();
+        let hints = [
+            (Action::Upgrade, "upgrade"),
+            (Action::Rollback, "rollback"),
+            (Action::Uninstall, "uninstall"),
+            (Action::Open, "open in search"),
+        ];
+        for (action, hint) in hints.iter() {
+            title.0.push(Span::raw(format!(
+                "  [{}] {}",
+                self.keymap.keys(*action),
+                hint
+            )));
         }
-        self.found_assets = if let Some(re) = &self.asset_re {
-            if let Some(selected_release) = self.found_releases.get(self.selected_release) {
-                common::find_asset_from(&re, &selected_release.assets)
-            } else {
-                vec![]
-            }
+        self.installed_area = area;
+        let rows = area.height.saturating_sub(3).max(1) as usize;
+        self.installed_offset = table_offset(self.installed_offset, self.installed_selected, rows);
+        self.installed_table.select(if self.installed.is_empty() {
+            None
         } else {
-            if let Some(selected_release) = self.found_releases.get(self.selected_release) {
-                selected_release.assets.clone()
-            } else {
-                vec![]
+            Some(self.installed_selected)
+        });
+        f.render_stateful_widget(
+            table.block(block.title(title)),
+            area,
+            &mut self.installed_table,
+        );
+    }
+
+    /// Read the global store again, newest tags already looked up are kept
+    fn refresh_installed(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let store = commands::global_store()?;
+        let config = Config::load_global()?;
+        let old = mem::take(&mut self.installed);
+        for name in store.packages()? {
+            let configured = config.packages.get(&name);
+            let pkg = configured.cloned().or_else(|| store.package(&name));
+            let repo = pkg.as_ref().and_then(|pkg| {
+                let spec = pkg.repo.parse::<RepoSpec>().ok()?.to_string();
+                let (owner, repo) = spec.split_once('/')?;
+                Some((owner.to_owned(), repo.to_owned()))
+            });
+            let known = old
+                .iter()
+                .find(|row| row.repo.is_some() && row.repo == repo);
+            let mut row = Installed {
+                current: store.current(&name),
+                latest: known.and_then(|row| row.latest.clone()),
+                latest_requested: known.is_some_and(|row| row.latest_requested),
+                pinned: configured
+                    .is_some_and(|pkg| matches!(pkg.release_finder(), Ok(ReleaseFinder::ByTag(_)))),
+                broken: false,
+                name,
+                pkg,
+                repo,
+            };
+            row.broken = store.is_broken(&row.name, row.bins());
+            if let (Some((owner, repo)), false) = (&row.repo, row.latest_requested) {
+                self.try_dl_repo.send(DownloadPlease::LatestRelease {
+                    owner: owner.clone(),
+                    repo: repo.clone(),
+                })?;
+                row.latest_requested = true;
             }
-        };
+            self.installed.push(row);
+        }
+        self.installed_selected = self
+            .installed_selected
+            .min(self.installed.len().saturating_sub(1));
         Ok(())
     }
 
-    fn on_key(&mut self, key: KeyCode) -> Result<(), Box<dyn std::error::Error>> {
-        match key {
-            KeyCode::Char(c) => {
-                let f = self.selected_field_mut();
-                f.push(c);
-
-                match self.field_selected {
-                    // Only Update the repo on Enter
-                    0 | 1 => {}
-                    // Update the regexes
-                    2 => self.update_release_re(true)?,
-                    3 => self.update_asset_re(true)?,
-                    _ => panic!("Invalid field"),
-                };
-            }
-            KeyCode::Backspace => {
-                let f = self.selected_field_mut();
-                if f == ".*" {
-                    f.clear();
-                } else {
-                    f.pop();
-                    if f == "" {
-                        *f = String::from(".*");
-                    }
-                }
+    fn show_installed(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.tab = Tab::Installed;
+        self.refresh_installed()
+    }
 
-                match self.field_selected {
-                    // Only Update the repo on Enter
-                    0 | 1 => {}
-                    // Update the regexes
-                    2 => self.update_release_re(true)?,
-                    3 => self.update_asset_re(true)?,
-                    _ => panic!("Invalid field"),
-                };
-            }
-            KeyCode::Enter => {
-                match self.field_selected {
-                    // Update the repo
-                    0 | 1 => self.update_release_list()?,
-                    2 => self.update_release_re(true)?,
-                    3 => self.update_asset_re(true)?,
-                    _ => panic!("Invalid field"),
-                };
-            }
-            KeyCode::Left => {
-                self.field_selected = self.field_selected.saturating_sub(1);
-            }
-            KeyCode::Tab | KeyCode::Right => {
-                self.field_selected = self.field_selected.add(1).min(3);
-            }
-            KeyCode::Up => {
-                self.field_selected = match self.field_selected {
-                    i @ (2 | 3) => i - 2,
-                    i => i,
-                };
-            }
-            KeyCode::Down => {
-                self.field_selected = match self.field_selected {
-                    i @ (0 | 1) => i + 2,
-                    i => i,
-                };
+    /// Install the newest release over the selected package
+    fn upgrade_installed(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let row = match self.installed.get(self.installed_selected) {
+            Some(row) => row.clone(),
+            None => return Ok(()),
+        };
+        let mut pkg = match row.pkg {
+            Some(pkg) => pkg,
+            None => {
+                self.error(format!("No repository known for {}", row.name));
+                return Ok(());
             }
-            KeyCode::Home => {}
-            KeyCode::End => {}
-            KeyCode::PageUp => {}
-            KeyCode::PageDown => {}
-            KeyCode::BackTab => {}
-            KeyCode::Delete => {}
-            KeyCode::Insert => {}
-            KeyCode::F(_) => {}
-            KeyCode::Null => {}
-            KeyCode::Esc => {}
         };
-        Ok(())
+        if row.pinned {
+            self.error(format!(
+                "{} is pinned to {} in the global package file",
+                row.name,
+                pkg.release.unwrap_or_default()
+            ));
+            return Ok(());
+        }
+        pkg.release = None;
+        let name = row.name;
+        let title = format!("upgrade {}", name);
+        self.queue_job(title, |job| DownloadPlease::Install(job, name, pkg))
     }
 
-    fn on_tick(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let rels = self.get_dl_repo.try_iter().last();
-        if let Some(rels) = rels {
-            self.all_releases = rels;
-            self.found_releases = self.all_releases.clone();
-            self.selected_col = 0;
-            self.selected_asset = 0;
-            self.selected_release = 0;
-            self.update_release_re(true)?;
-            self.update_asset_re(true)?;
+    /// Link the version installed before the current one
+    fn rollback_installed(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let row = match self.installed.get(self.installed_selected) {
+            Some(row) => row.clone(),
+            None => return Ok(()),
+        };
+        let store = commands::global_store()?;
+        match store.previous(&row.name)? {
+            Some(tag) => {
+                store.link(&row.name, &tag, row.bins())?;
+                self.info(format!("Rolled {} back to {}", row.name, tag));
+            }
+            None => self.error(format!("No older version of {} is installed", row.name)),
         }
-        Ok(())
+        self.refresh_installed()
     }
 
-    fn block() -> tui::widgets::Block<'static> {
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::White))
-            .border_type(BorderType::Rounded)
-            .style(Style::default().bg(Color::Black))
+    fn uninstall_installed(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let row = match self.installed.get(self.installed_selected) {
+            Some(row) => row.clone(),
+            None => return Ok(()),
+        };
+        commands::global_store()?.uninstall(&row.name, row.bins())?;
+        self.info(format!("Uninstalled {}", row.name));
+        self.refresh_installed()
     }
-}
+
+    /// Release notes since the selected package's version up to the latest release
+    fn changelog_installed(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let row = match self.installed.get(self.installed_selected) {
+            Some(row) => row.clone(),
+            None => return Ok(()),
+        };
+        match (row.pkg, row.current) {
+            (Some(pkg), Some(from)) => self.request_changelog(pkg.repo, from, None),
+            (None, _) => {
+                self.error(format!("No repository known for {}", row.name));
+                Ok(())
+            }
+            (_, None) => {
+                self.error(format!("No version of {} is linked", row.name));
+                Ok(())
+            }
+        }
+    }
+
+    /// List the releases of the selected package in the search tab
+    fn open_installed(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let row = match self.installed.get(self.installed_selected) {
+            Some(row) => row.clone(),
+            None => return Ok(()),
+        };
+        let (owner, repo) = match row.repo {
+            Some(repo) => repo,
+            None => {
+                self.error(format!("No repository known for {}", row.name));
+                return Ok(());
+            }
+        };
+        self.owner.set(owner);
+        self.repo.set(repo);
+        self.tab = Tab::Search;
+        self.focus = Focus::Releases;
+        self.close_dropdown();
+        self.update_release_list()
+    }
+
+    fn on_installed_key(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let prompt = self.uninstall_prompt.take();
+        let last = self.installed.len().saturating_sub(1);
+        let actions = [
+            Action::Up,
+            Action::Down,
+            Action::Top,
+            Action::Bottom,
+            Action::Upgrade,
+            Action::Rollback,
+            Action::Uninstall,
+            Action::Open,
+            Action::Changelog,
+            Action::SearchTab,
+            Action::StarredTab,
+            Action::Back,
+        ];
+        match self.keymap.action(key, &actions) {
+            Some(Action::Up) => self.installed_selected = self.installed_selected.saturating_sub(1),
+            Some(Action::Down) => self.installed_selected = (self.installed_selected + 1).min(last),
+            Some(Action::Top) => self.installed_selected = 0,
+            Some(Action::Bottom) => self.installed_selected = last,
+            Some(Action::Upgrade) => self.upgrade_installed()?,
+            Some(Action::Rollback) => self.rollback_installed()?,
+            Some(Action::Uninstall) => {
+                let name = match self.installed.get(self.installed_selected) {
+                    Some(row) => row.name.clone(),
+                    None => return Ok(()),
+                };
+                if prompt.as_ref() == Some(&name) {
+                    self.uninstall_installed()?;
+                } else {
+                    let keys = self.keymap.keys(Action::Uninstall);
+                    self.info(format!("Press {} again to uninstall {}", keys, name));
+                    self.uninstall_prompt = Some(name);
+                }
+            }
+            Some(Action::Open) => self.open_installed()?,
+            Some(Action::Changelog) => self.changelog_installed()?,
+            Some(Action::StarredTab) => self.show_starred()?,
+            Some(Action::SearchTab) | Some(Action::Back) => self.tab = Tab::Search,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn draw_starred(&mut self, f: &mut Frame<impl Backend>, block: Block, area: Rect) {
+        let rows = self.starred.iter().map(|row| {
+            Row::new(vec![
+                format!("{}/{}", row.summary.owner, row.summary.repo),
+                row.tag.clone(),
+                row.assets.iter().map(|ass| ass.name.as_str()).join(", "),
+                row.summary.description.clone().unwrap_or_default(),
+            ])
+        });
+        let table = Table::new(rows.collect_vec())
+            .widths(&[
+                Constraint::Percentage(25),
+                Constraint::Percentage(15),
+                Constraint::Percentage(30),
+                Constraint::Min(10),
+            ])
+            .header(Row::new(vec![
+                "repository",
+                "latest",
+                "assets for this platform",
+                "description",
+            ]))
+            .highlight_style(self.theme.selected)
+            .highlight_symbol("> ");
+        let mut title = self.tabs_title();
+        if self.starred_loading {
+            title
+                .0
+                .push(Span::raw("  looking up the latest releases..."));
+        } else {
+            let hints = [
+                (Action::Install, "install"),
+                (Action::Open, "open in search"),
+            ];
+            for (action, hint) in hints.iter() {
+                title.0.push(Span::raw(format!(
+                    "  [{}] {}",
+                    self.keymap.keys(*action),
+                    hint
+                )));
+            }
+        }
+        self.starred_area = area;
+        let rows = area.height.saturating_sub(3).max(1) as usize;
+        self.starred_offset = table_offset(self.starred_offset, self.starred_selected, rows);
+        self.starred_table.select(if self.starred.is_empty() {
+            None
+        } else {
+            Some(self.starred_selected)
+        });
+        f.render_stateful_widget(
+            table.block(block.title(title)),
+            area,
+            &mut self.starred_table,
+        );
+    }
+
+    /// The starred tab, listing the repositories the first time
+    fn show_starred(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.tab = Tab::Starred;
+        if self.starred_loaded || self.starred_loading {
+            return Ok(());
+        }
+        self.list_starred(self.starred_watched)
+    }
+
+    /// Look up the starred or watched repositories again
+    fn list_starred(&mut self, watched: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.tab = Tab::Starred;
+        self.starred_watched = watched;
+        self.starred_loading = true;
+        self.starred.clear();
+        self.starred_selected = 0;
+        self.try_dl_repo.send(DownloadPlease::Starred { watched })?;
+        Ok(())
+    }
+
+    /// Install the latest release of the selected repository, with its first asset for this platform
+    fn install_starred(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let row = match self.starred.get(self.starred_selected) {
+            Some(row) => row.clone(),
+            None => return Ok(()),
+        };
+        let (name, pkg) = row.package()?;
+        let title = format!("install {} {}", name, row.tag);
+        self.queue_job(title, |job| DownloadPlease::Install(job, name, pkg))
+    }
+
+    /// List the releases of the selected repository in the search tab
+    fn open_starred(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let row = match self.starred.get(self.starred_selected) {
+            Some(row) => row.clone(),
+            None => return Ok(()),
+        };
+        self.owner.set(row.summary.owner);
+        self.repo.set(row.summary.repo);
+        self.tab = Tab::Search;
+        self.focus = Focus::Releases;
+        self.close_dropdown();
+        self.update_release_list()
+    }
+
+    fn on_starred_key(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let last = self.starred.len().saturating_sub(1);
+        let actions = [
+            Action::Up,
+            Action::Down,
+            Action::Top,
+            Action::Bottom,
+            Action::Install,
+            Action::Open,
+            Action::SearchTab,
+            Action::InstalledTab,
+            Action::Back,
+        ];
+        match self.keymap.action(key, &actions) {
+            Some(Action::Up) => self.starred_selected = self.starred_selected.saturating_sub(1),
+            Some(Action::Down) => self.starred_selected = (self.starred_selected + 1).min(last),
+            Some(Action::Top) => self.starred_selected = 0,
+            Some(Action::Bottom) => self.starred_selected = last,
+            Some(Action::Install) => self.install_starred()?,
+            Some(Action::Open) => self.open_starred()?,
+            Some(Action::InstalledTab) => self.show_installed()?,
+            Some(Action::SearchTab) | Some(Action::Back) => self.tab = Tab::Search,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn draw_dropdown(&self, f: &mut Frame<impl Backend>, block: Block, area: Rect) {
+        let rows = self.repo_candidates.iter().map(|c| {
+            Row::new(vec![
+                format!("{}/{}", c.summary.owner, c.summary.repo),
+                match c.remembered {
+                    Some((true, _)) => String::from("favourite"),
+                    Some((false, _)) => String::from("recent"),
+                    None => format!("★ {}", c.summary.stars),
+                },
+                c.latest.clone().unwrap_or_default(),
+                c.summary.description.clone().unwrap_or_default(),
+            ])
+        });
+        let table = Table::new(rows.collect_vec())
+            .widths(&[
+                Constraint::Percentage(30),
+                Constraint::Length(8),
+                Constraint::Length(14),
+                Constraint::Min(10),
+            ])
+            .header(Row::new(vec![
+                "repository",
+                "stars",
+                "latest",
+                "description",
+            ]))
+            .highlight_style(self.theme.selected)
+            .block(block);
+        let mut state = TableState::default();
+        state.select(self.candidate_selected);
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(table, area, &mut state);
+    }
+
+    /// Release notes rendered from Markdown, or the details of the focused asset
+    fn description(&mut self) -> Text<'static> {
+        let rel = match self.found_releases.get(self.selected_release) {
+            Some(rel) => rel,
+            None => return Text::default(),
+        };
+        let (owner, repo) = &self.listed_repo;
+        let key = match (self.focus, self.selected_asset()) {
+            (Focus::Assets, Some(ass)) => format!("{}/{} asset {}", owner, repo, ass.id),
+            (Focus::Assets, None) => return Text::default(),
+            _ => format!("{}/{} release {}", owner, repo, rel.tag_name),
+        };
+        if let Some((cached, text)) = &self.desc_cache {
+            if *cached == key {
+                return text.clone();
+            }
+        }
+        let text = match (self.focus, self.selected_asset()) {
+            (Focus::Assets, Some(ass)) => asset_details(ass, &rel.assets),
+            _ => markdown::render(rel.body.as_deref().unwrap_or("")),
+        };
+        self.desc_cache = Some((key, text.clone()));
+        self.desc_scroll = 0;
+        text
+    }
+
+    fn draw_description(&mut self, f: &mut Frame<impl Backend>, block: Block, area: Rect) {
+        let (name, desc) = match &self.changelog {
+            Some((title, text)) => (title.clone(), text.clone()),
+            None => (String::from("Description"), self.description()),
+        };
+        self.desc_rows = block.inner(area).height.max(1);
+        // Wrapping only adds lines, so this always leaves the last line reachable
+        self.desc_scroll = self.desc_scroll.min(desc.height().saturating_sub(1) as u16);
+        let keys = |action| self.keymap.keys(action);
+        let title = if self.desc_fullscreen {
+            format!(
+                "{} [{}/{} to close]",
+                name,
+                keys(Action::Fullscreen),
+                keys(Action::Back)
+            )
+        } else {
+            format!(
+                "{} [{}/{} scroll, {} fullscreen]",
+                name,
+                keys(Action::ScrollDown),
+                keys(Action::ScrollUp),
+                keys(Action::Fullscreen)
+            )
+        };
+        let desc = Paragraph::new(desc)
+            .wrap(Wrap { trim: false })
+            .scroll((self.desc_scroll, 0))
+            .block(block.title(title));
+        f.render_widget(desc, area);
+    }
+
+    fn scroll_description(&mut self, lines: i32) {
+        self.desc_scroll = (self.desc_scroll as i32 + lines).max(0) as u16;
+    }
+
+    fn on_description_key(&mut self, key: KeyEvent) {
+        let page = self.desc_rows as i32;
+        let actions = [
+            Action::Down,
+            Action::ScrollDown,
+            Action::Up,
+            Action::ScrollUp,
+            Action::PageDown,
+            Action::PageUp,
+            Action::Top,
+            Action::Bottom,
+            Action::Back,
+            Action::Fullscreen,
+        ];
+        match self.keymap.action(key, &actions) {
+            Some(Action::Down) | Some(Action::ScrollDown) => self.scroll_description(1),
+            Some(Action::Up) | Some(Action::ScrollUp) => self.scroll_description(-1),
+            Some(Action::PageDown) => self.scroll_description(page),
+            Some(Action::PageUp) => self.scroll_description(-page),
+            Some(Action::Top) => self.desc_scroll = 0,
+            Some(Action::Bottom) => self.desc_scroll = u16::MAX,
+            Some(Action::Back) | Some(Action::Fullscreen) => {
+                self.desc_fullscreen = false;
+                self.changelog = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn draw_log(&self, f: &mut Frame<impl Backend>, block: Block, area: Rect) {
+        let rows = block.inner(area).height as usize;
+        let lines = self.log[self.log.len().saturating_sub(rows)..]
+            .iter()
+            .map(|line| {
+                Spans::from(vec![
+                    Span::styled(
+                        line.at.format("%H:%M:%S ").to_string(),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(line.text.clone(), Self::level_style(line.level)),
+                ])
+            })
+            .collect_vec();
+        f.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    /// An invalid regex in the focused field, otherwise the newest log line
+    fn draw_status(&self, f: &mut Frame<impl Backend>, area: Rect) {
+        if let Some(command) = &self.command_prompt {
+            let line = Span::styled(format!(":{}", command), self.theme.field);
+            f.render_widget(Paragraph::new(line), area);
+            return;
+        }
+        let invalid = match (self.focus, self.field_selected) {
+            (Focus::Fields, 2) => self.release_re_error.as_deref(),
+            (Focus::Fields, 3) => self.asset_re_error.as_deref(),
+            _ => None,
+        };
+        let (text, level) = match (invalid, self.log.last()) {
+            (Some(e), _) => (e, Level::Error),
+            (None, Some(line)) => (line.text.as_str(), line.level),
+            (None, None) => ("", Level::Info),
+        };
+        f.render_widget(
+            Paragraph::new(Span::styled(text, Self::level_style(level))),
+            area,
+        );
+    }
+
+    fn level_style(level: Level) -> Style {
+        match level {
+            Level::Info => Style::default().fg(Color::White),
+            Level::Error => Style::default().fg(Color::Red),
+        }
+    }
+
+    fn push_log(&mut self, level: Level, text: String) {
+        if self.log.len() == LOG_LINES {
+            self.log.remove(0);
+        }
+        self.log.push(LogLine {
+            at: Local::now(),
+            level,
+            text,
+        });
+    }
+    fn info(&mut self, text: impl Into<String>) {
+        self.push_log(Level::Info, text.into())
+    }
+    fn error(&mut self, text: impl Into<String>) {
+        self.push_log(Level::Error, text.into())
+    }
+
+    fn jobs_height(&self) -> u16 {
+        if self.jobs.is_empty() {
+            0
+        } else {
+            2 + self.jobs.len().min(JOB_ROWS) as u16
+        }
+    }
+
+    fn draw_jobs(&self, f: &mut Frame<impl Backend>, block: Block, area: Rect) {
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        let first = (self.job_selected + 1).saturating_sub(JOB_ROWS);
+        for (row, (i, job)) in self
+            .jobs
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(JOB_ROWS)
+            .enumerate()
+        {
+            let color = match job.state {
+                JobState::Queued => Color::Gray,
+                JobState::Running => Color::Cyan,
+                JobState::Finished(_) => Color::Green,
+                JobState::Failed(_) => Color::Red,
+                JobState::Cancelled => Color::DarkGray,
+            };
+            let label_style = if self.focus == Focus::Jobs && self.job_selected == i {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            let gauge = LineGauge::default()
+                .ratio(job.ratio())
+                .label(Span::styled(job.label(), label_style))
+                .gauge_style(Style::default().fg(color).bg(Color::Black));
+            let line = Rect {
+                y: inner.y + row as u16,
+                height: 1,
+                ..inner
+            };
+            f.render_widget(gauge, line);
+        }
+    }
+
+    /// Add a job to the panel and hand it to the job worker
+    fn queue_job(
+        &mut self,
+        title: String,
+        req: impl FnOnce(JobHandle) -> DownloadPlease,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let handle = JobHandle {
+            id: self.next_job,
+            cancel: Arc::new(AtomicBool::new(false)),
+        };
+        self.next_job += 1;
+        self.try_dl_repo.send(req(handle.clone()))?;
+        self.jobs.push(Job {
+            handle,
+            title,
+            state: JobState::Queued,
+            done: 0,
+            total: None,
+            started: None,
+        });
+        Ok(())
+    }
+
+    fn job_mut(&mut self, id: JobId) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.handle.id == id)
+    }
+
+    fn cancel_selected_job(&mut self) {
+        if let Some(job) = self.jobs.get_mut(self.job_selected) {
+            match job.state {
+                JobState::Queued => {
+                    // The job worker skips it when it gets to it
+                    job.handle.cancel.store(true, Ordering::Relaxed);
+                    job.state = JobState::Cancelled;
+                }
+                JobState::Running => job.handle.cancel.store(true, Ordering::Relaxed),
+                _ => {}
+            }
+        }
+    }
+
+    /// Cancel every queued or running job on quit, returns how many there were
+    fn cancel_jobs(&mut self) -> usize {
+        let unfinished = self
+            .jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Queued | JobState::Running));
+        unfinished
+            .map(|job| job.handle.cancel.store(true, Ordering::Relaxed))
+            .count()
+    }
+
+    fn on_jobs_key(&mut self, key: KeyEvent) {
+        let actions = [
+            Action::Up,
+            Action::Down,
+            Action::Cancel,
+            Action::Prev,
+            Action::Next,
+            Action::Back,
+        ];
+        match self.keymap.action(key, &actions) {
+            Some(Action::Up) if self.job_selected == 0 => self.focus = Focus::Buttons,
+            Some(Action::Up) => self.job_selected -= 1,
+            Some(Action::Down) => {
+                self.job_selected = (self.job_selected + 1).min(self.jobs.len().saturating_sub(1))
+            }
+            Some(Action::Cancel) => self.cancel_selected_job(),
+            Some(Action::Prev) => self.focus = Focus::Buttons,
+            Some(Action::Next) | Some(Action::Back) => self.focus_field(0),
+            _ => {}
+        }
+    }
+
+    fn selected_asset(&self) -> Option<&Asset> {
+        self.found_assets.get(self.selected_asset)
+    }
+
+    /// Name for the package, the repository name without the owner
+    /// The package name of the listed repository, the fields may have been edited since
+    fn package_name(&self) -> String {
+        let (owner, repo) = &self.listed_repo;
+        format!("{}/{}", owner, repo)
+            .parse::<RepoSpec>()
+            .map(|spec| spec.package_name())
+            .unwrap_or_else(|_| repo.clone())
+    }
+
+    /// A package entry for the listed repository, which the selection and filters belong to
+    fn listed_package(
+        &self,
+        release: Option<&str>,
+        asset: Option<&str>,
+    ) -> Result<(String, Package), Box<dyn std::error::Error>> {
+        let (owner, repo) = &self.listed_repo;
+        if owner.is_empty() || repo.is_empty() {
+            return Err("No repository listed".into());
+        }
+        commands::package(owner, repo, release, asset, None)
+    }
+
+    /// Release notes since the installed version of the package up to the selected release
+    fn changelog_selected(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let to = match self.found_releases.get(self.selected_release) {
+            Some(rel) => rel.tag_name.clone(),
+            None => {
+                self.error("No release selected");
+                return Ok(());
+            }
+        };
+        let name = self.package_name();
+        match commands::global_store()?.current(&name) {
+            Some(from) => {
+                let (owner, repo) = &self.listed_repo;
+                let repo = format!("{}/{}", owner, repo);
+                self.request_changelog(repo, from, Some(to))
+            }
+            None => {
+                self.error(format!("{} is not installed", name));
+                Ok(())
+            }
+        }
+    }
+
+    fn request_changelog(
+        &mut self,
+        repo: String,
+        from: String,
+        to: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.info(format!(
+            "Fetching the release notes of {} after {}",
+            repo, from
+        ));
+        self.try_dl_repo
+            .send(DownloadPlease::Changelog { repo, from, to })?;
+        Ok(())
+    }
+
+    fn press(&mut self, button: Button) -> Result<(), Box<dyn std::error::Error>> {
+        match button {
+            Button::Install => {
+                let (rel, ass) = match (
+                    self.found_releases.get(self.selected_release),
+                    self.selected_asset(),
+                ) {
+                    (Some(rel), Some(ass)) => (rel, ass),
+                    _ => {
+                        self.error("No asset selected");
+                        return Ok(());
+                    }
+                };
+                // Pin exactly what is selected, not whatever the fields match first
+                let (name, pkg) = self.listed_package(
+                    Some(&format!("t:{}", rel.tag_name)),
+                    Some(&format!("^{}$", regex::escape(&ass.name))),
+                )?;
+                let title = format!("install {} {}", name, rel.tag_name);
+                self.queue_job(title, |job| DownloadPlease::Install(job, name, pkg))?;
+            }
+            Button::Download => match self.selected_asset() {
+                Some(ass) => self.download_prompt = Some(ass.name.clone()),
+                None => self.error("No asset selected"),
+            },
+            Button::Save => self.save(None)?,
+            Button::Link => match self.selected_asset() {
+                Some(ass) => {
+                    let url = ass.browser_download_url.to_string();
+                    if copy_to_clipboard(&url) {
+                        self.info("Copied the link");
+                    } else {
+                        self.info("No clipboard, the link is printed on exit");
+                        self.print_on_exit.push(url);
+                    }
+                }
+                None => self.error("No asset selected"),
+            },
+        }
+        Ok(())
+    }
+
+    /// Save the filters as a package entry for the listed repository in `config`,
+    /// the global package file by default
+    fn save(&mut self, config: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+        // Leave out the fields that match everything
+        let fuzzy = self.fuzzy;
+        let field = |f: &str| match f {
+            "" | ".*" | "*" => None,
+            f if fuzzy => Some(format!("f:{}", fuzzy_pattern(f))),
+            f => Some(f.to_owned()),
+        };
+        let (name, mut pkg) = self.listed_package(None, None)?;
+        pkg.release = field(self.search_rels.text());
+        pkg.asset = field(self.search_assets.text());
+        let path = commands::save(config, &name, pkg)?;
+        self.info(format!("Saved {} to {}", name, path.display()));
+        Ok(())
+    }
+
+    fn download_selected(&mut self, path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        match self.selected_asset().cloned() {
+            Some(ass) => {
+                let title = format!("download {}", ass.name);
+                let repo = format!("{}/{}", self.listed_repo.0, self.listed_repo.1);
+                self.queue_job(title, |job| DownloadPlease::Download(job, repo, ass, path))
+            }
+            None => {
+                self.error("No asset selected");
+                Ok(())
+            }
+        }
+    }
+
+    fn on_prompt_key(&mut self, key: KeyCode) -> Result<(), Box<dyn std::error::Error>> {
+        let path = match &mut self.download_prompt {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        match key {
+            KeyCode::Char(c) => path.push(c),
+            KeyCode::Backspace => {
+                path.pop();
+            }
+            KeyCode::Esc => self.download_prompt = None,
+            KeyCode::Enter => {
+                let path = commands::expand_home(path);
+                self.download_prompt = None;
+                self.download_selected(path)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn on_command_key(&mut self, key: KeyCode) -> Result<(), Box<dyn std::error::Error>> {
+        let line = match &mut self.command_prompt {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        match key {
+            KeyCode::Char(c) => line.push(c),
+            // Like in vim, deleting the last character closes the palette
+            KeyCode::Backspace if line.is_empty() => self.command_prompt = None,
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            KeyCode::Esc => self.command_prompt = None,
+            KeyCode::Enter => {
+                let line = self.command_prompt.take().unwrap_or_default();
+                self.run_command(&line)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Run a line of the command palette, see `COMMANDS`
+    fn run_command(&mut self, line: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let line = line.trim();
+        let (command, arg) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(c, arg)| (c, arg.trim()));
+        match (command, arg) {
+            ("", _) => {}
+            ("install", _) => self.press(Button::Install)?,
+            ("download", "") => self.download_selected(PathBuf::from("."))?,
+            ("download", path) => self.download_selected(commands::expand_home(path))?,
+            ("save", "") => self.save(None)?,
+            ("save", path) => self.save(Some(&commands::expand_home(path)))?,
+            ("link", _) => self.press(Button::Link)?,
+            ("repo", repo) => {
+                let link = repo.parse::<RepoLink>()?;
+                self.fill_from_link(&link);
+                self.tab = Tab::Search;
+                self.close_dropdown();
+                self.update_release_list()?;
+                self.update_release_re(true)?;
+                self.update_asset_re(true)?;
+                self.focus = Focus::Releases;
+            }
+            ("tag", "") => self.error("tag needs a TAG"),
+            ("tag", tag) => {
+                self.search_rels.set(if self.fuzzy {
+                    tag.to_owned()
+                } else {
+                    format!("^{}$", regex::escape(tag))
+                });
+                self.tab = Tab::Search;
+                self.update_release_re(true)?;
+                self.focus = Focus::Releases;
+            }
+            ("asset", filter) => {
+                self.search_assets.set(filter.to_owned());
+                self.tab = Tab::Search;
+                self.update_asset_re(true)?;
+                self.focus = Focus::Assets;
+            }
+            ("fuzzy", _) => self.toggle_fuzzy()?,
+            ("favourite", _) => self.toggle_favourite(),
+            ("changelog", _) => self.changelog_selected()?,
+            ("installed", _) => self.show_installed()?,
+            ("starred", _) => self.list_starred(false)?,
+            ("watched", _) => self.list_starred(true)?,
+            ("search", _) => self.tab = Tab::Search,
+            ("help", _) => self.help_scroll = Some(0),
+            ("quit", _) | ("q", _) => self.quit = true,
+            (command, _) => self.error(format!(
+                "Unknown command {}, {} lists them",
+                command,
+                self.keymap.keys(Action::Help)
+            )),
+        }
+        Ok(())
+    }
+
+    fn on_button_key(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let actions = [
+            Action::Left,
+            Action::Right,
+            Action::Select,
+            Action::Up,
+            Action::Prev,
+            Action::Down,
+            Action::Next,
+            Action::Back,
+        ];
+        match self.keymap.action(key, &actions) {
+            Some(Action::Left) => self.button_selected = self.button_selected.saturating_sub(1),
+            Some(Action::Right) => {
+                self.button_selected = (self.button_selected + 1).min(Button::ALL.len() - 1)
+            }
+            Some(Action::Select) => self.press(Button::ALL[self.button_selected])?,
+            Some(Action::Up) | Some(Action::Prev) => self.focus = Focus::Assets,
+            Some(Action::Down) | Some(Action::Next) if !self.jobs.is_empty() => {
+                self.focus = Focus::Jobs
+            }
+            Some(Action::Next) | Some(Action::Back) => self.focus_field(0),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The selected row stands out more in the focused table
+    fn highlighted<'a>(&self, table: Table<'a>, pane: Focus) -> Table<'a> {
+        if self.focus == pane {
+            table
+                .highlight_style(self.theme.selected)
+                .highlight_symbol("> ")
+        } else {
+            table
+                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                .highlight_symbol("  ")
+        }
+    }
+
+    /// Move the highlighted row of the focused table,
+    /// `to` gets the current row and the number of rows
+    fn move_selection(
+        &mut self,
+        to: impl FnOnce(usize, usize) -> usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self.focus {
+            Focus::Releases if !self.found_releases.is_empty() => {
+                let len = self.found_releases.len();
+                let row = to(self.selected_release, len).min(len - 1);
+                if row != self.selected_release {
+                    // A different release has different assets
+                    self.selected_release = row;
+                    self.selected_asset = 0;
+                    self.update_asset_re(false)?;
+                }
+                self.load_more_releases()?;
+            }
+            Focus::Assets if !self.found_assets.is_empty() => {
+                let len = self.found_assets.len();
+                self.selected_asset = to(self.selected_asset, len).min(len - 1);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn on_table_key(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let page = self.table_rows;
+        let row = match self.focus {
+            Focus::Releases => self.selected_release,
+            _ => self.selected_asset,
+        };
+        let releases = self.focus == Focus::Releases;
+        // Page Down before Select, they share the space bar
+        let actions = [
+            Action::Up,
+            Action::Down,
+            Action::PageUp,
+            Action::PageDown,
+            Action::Top,
+            Action::Bottom,
+            Action::Left,
+            Action::Right,
+            Action::Select,
+            Action::Next,
+            Action::Prev,
+            Action::Back,
+        ];
+        match self.keymap.action(key, &actions) {
+            Some(Action::Up) if row == 0 => self.focus_field(if releases { 2 } else { 3 }),
+            Some(Action::Up) => self.move_selection(|i, _| i - 1)?,
+            Some(Action::Down) => self.move_selection(|i, _| i + 1)?,
+            Some(Action::PageUp) => self.move_selection(|i, _| i.saturating_sub(page))?,
+            Some(Action::PageDown) => self.move_selection(|i, _| i + page)?,
+            Some(Action::Top) => self.move_selection(|_, _| 0)?,
+            Some(Action::Bottom) => self.move_selection(|_, n| n - 1)?,
+            Some(Action::Left) | Some(Action::Prev) if !releases => self.focus = Focus::Releases,
+            Some(Action::Right) | Some(Action::Select) | Some(Action::Next) if releases => {
+                self.focus = Focus::Assets
+            }
+            Some(Action::Next) => self.focus = Focus::Buttons,
+            Some(Action::Prev) => self.focus_field(3),
+            Some(Action::Back) => self.focus_field(self.field_selected),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn dropdown_open(&self) -> bool {
+        self.typing() && self.field_selected < 2 && !self.repo_candidates.is_empty()
+    }
+
+    /// The GitHub search query for the Owner and Repo fields,
+    /// `None` when they match anything or hold a link
+    fn search_query(&self) -> Option<String> {
+        let (owner, repo) = (
+            search_term(self.owner.text()),
+            search_term(self.repo.text()),
+        );
+        if owner.contains(&['/', ':'][..]) || repo.contains(&['/', ':'][..]) {
+            return None;
+        }
+        match (owner.is_empty(), repo.is_empty()) {
+            (true, true) => None,
+            (true, false) => Some(format!("{} in:name", repo)),
+            (false, true) => Some(format!("user:{}", owner)),
+            (false, false) => Some(format!("{} in:name user:{}", repo, owner)),
+        }
+    }
+
+    fn search_repos(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        match self.search_query() {
+            None => self.repo_candidates.clear(),
+            Some(query) if query != self.search_sent => {
+                self.repo_candidates.clear();
+                self.search_sent = query.clone();
+                self.try_dl_repo.send(DownloadPlease::SearchRepos(query))?;
+            }
+            Some(_) => {}
+        }
+        self.suggest();
+        Ok(())
+    }
+
+    /// Put the remembered repositories matching the Owner and Repo fields in front of the search results
+    fn suggest(&mut self) {
+        let (owner, repo) = (
+            search_term(self.owner.text()),
+            search_term(self.repo.text()),
+        );
+        let remembered = self
+            .state
+            .suggestions(&owner, &repo)
+            .into_iter()
+            .map(|(favourite, fields)| {
+                let filters = [("release", &fields.release), ("asset", &fields.asset)]
+                    .iter()
+                    .filter(|(_, filter)| !filter.is_empty())
+                    .map(|(name, filter)| format!("{} {}", name, filter))
+                    .join(", ");
+                Candidate {
+                    summary: RepoSummary {
+                        owner: fields.owner.clone(),
+                        repo: fields.repo.clone(),
+                        description: Some(filters),
+                        stars: 0,
+                    },
+                    remembered: Some((favourite, fields.clone())),
+                    latest: None,
+                    latest_requested: false,
+                }
+            })
+            .collect_vec();
+        let found = mem::take(&mut self.repo_candidates)
+            .into_iter()
+            .filter(|c| c.remembered.is_none())
+            .filter(|c| {
+                !remembered.iter().any(|r| {
+                    r.summary.owner.eq_ignore_ascii_case(&c.summary.owner)
+                        && r.summary.repo.eq_ignore_ascii_case(&c.summary.repo)
+                })
+            })
+            .collect_vec();
+        self.repo_candidates = remembered.into_iter().chain(found).collect();
+        self.candidate_selected = None;
+    }
+
+    fn select_candidate(&mut self, i: Option<usize>) -> Result<(), Box<dyn std::error::Error>> {
+        self.candidate_selected = i;
+        let candidates = &mut self.repo_candidates;
+        if let Some(c) = i.and_then(|i| candidates.get_mut(i)) {
+            if !c.latest_requested {
+                c.latest_requested = true;
+                c.latest = Some(String::from("..."));
+                self.try_dl_repo.send(DownloadPlease::LatestRelease {
+                    owner: c.summary.owner.clone(),
+                    repo: c.summary.repo.clone(),
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Keys for the repository dropdown, returns whether the key was used
+    fn on_dropdown_key(&mut self, key: KeyCode) -> Result<bool, Box<dyn std::error::Error>> {
+        let len = self.repo_candidates.len();
+        match (key, self.candidate_selected) {
+            (KeyCode::Down, None) => self.select_candidate(Some(0))?,
+            (KeyCode::Down, Some(i)) => self.select_candidate(Some((i + 1).min(len - 1)))?,
+            (KeyCode::Up, Some(0)) => self.select_candidate(None)?,
+            (KeyCode::Up, Some(i)) => self.select_candidate(Some(i - 1))?,
+            (KeyCode::Enter, Some(i)) => self.pick_candidate(i)?,
+            (KeyCode::Esc, _) => self.close_dropdown(),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// List the releases of a search result
+    fn pick_candidate(&mut self, i: usize) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((_, fields)) = self.repo_candidates[i].remembered.clone() {
+            self.close_dropdown();
+            return self.restore(fields);
+        }
+        let picked = self.repo_candidates[i].summary.clone();
+        self.owner.set(picked.owner);
+        self.repo.set(picked.repo);
+        self.close_dropdown();
+        self.update_release_list()?;
+        self.focus = Focus::Releases;
+        Ok(())
+    }
+
+    fn close_dropdown(&mut self) {
+        self.repo_candidates.clear();
+        self.candidate_selected = None;
+        self.search_due = None;
+    }
+
+    /// A link pasted into the Owner or Repo field fills in every field it has information for
+    fn expand_link(&mut self) {
+        if let Ok(link) = self.selected_field().text().parse::<RepoLink>() {
+            self.fill_from_link(&link);
+        }
+    }
+    fn fill_from_link(&mut self, link: &RepoLink) {
+        let spec = link.repo.to_string();
+        if let Some((owner, repo)) = spec.split_once('/') {
+            self.owner.set(owner.to_owned());
+            self.repo.set(repo.to_owned());
+        }
+        if let Some(tag) = &link.tag {
+            self.search_rels.set(format!("^{}$", regex::escape(tag)));
+        }
+        if let Some(asset) = link.asset_regex() {
+            self.search_assets.set(asset);
+        }
+    }
+    /// Start listing the releases of the repository in the fields from the first page
+    fn update_release_list(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.remember_filters();
+        self.listed_repo = (self.owner.text().to_owned(), self.repo.text().to_owned());
+        if let Some(key) = self.listed_key() {
+            let (owner, repo) = &self.listed_repo;
+            self.state.used(&key, owner, repo);
+        }
+        self.next_page = Some(1);
+        self.loading_releases = false;
+        self.request_release_page()
+    }
+    fn request_release_page(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let (Some(page), false) = (self.next_page, self.loading_releases) {
+            let (owner, repo) = self.listed_repo.clone();
+            self.try_dl_repo
+                .send(DownloadPlease::ReleasePage { owner, repo, page })?;
+            self.loading_releases = true;
+        }
+        Ok(())
+    }
+    /// Request the next page once the selection is within a screen of the end,
+    /// which also keeps loading while the filter matches too few releases to fill the table
+    fn load_more_releases(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.selected_release + self.table_rows >= self.found_releases.len() {
+            self.request_release_page()?;
+        }
+        Ok(())
+    }
+    fn on_release_page(
+        &mut self,
+        page: u32,
+        releases: Vec<Release>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if releases.is_empty() {
+            self.next_page = None;
+            return Ok(());
+        }
+        self.next_page = Some(page + 1);
+        if page == 1 {
+            self.all_releases = releases;
+            self.update_release_re(true)?;
+            self.update_asset_re(true)?;
+        } else {
+            let had_releases = !self.found_releases.is_empty();
+            if self.fuzzy {
+                // A better match may rank above the selection, so follow it by tag
+                let selected = self
+                    .found_releases
+                    .get(self.selected_release)
+                    .map(|rel| rel.tag_name.clone());
+                self.all_releases.extend(releases);
+                self.filter_releases();
+                self.selected_release = selected
+                    .and_then(|tag| {
+                        self.found_releases
+                            .iter()
+                            .position(|rel| rel.tag_name == tag)
+                    })
+                    .unwrap_or(0);
+            } else {
+                // Filtering keeps the order, so the rows already shown and the selection stay put
+                let found = self.find_releases(&releases);
+                self.all_releases.extend(releases);
+                for (rel, hits) in found {
+                    self.found_releases.push(rel);
+                    self.release_hits.push(hits);
+                }
+            }
+            if !had_releases {
+                self.update_asset_re(false)?;
+            }
+        }
+        self.load_more_releases()
+    }
+    /// The releases matching the Release field, with the matched characters when fuzzy
+    fn find_releases(&self, rels: &[Release]) -> Vec<(Release, Vec<usize>)> {
+        if self.fuzzy {
+            return common::find_release_fuzzy(fuzzy_pattern(self.search_rels.text()), rels);
+        }
+        let found = match &self.release_re {
+            Some(re) => common::find_release_from(re, rels),
+            None => rels.to_vec(),
+        };
+        found.into_iter().map(|rel| (rel, Vec::new())).collect()
+    }
+    fn filter_releases(&mut self) {
+        let (found, hits) = self.find_releases(&self.all_releases).into_iter().unzip();
+        self.found_releases = found;
+        self.release_hits = hits;
+    }
+    fn update_release_re(&mut self, recompile: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if self.fuzzy {
+            // Every pattern is a valid fuzzy pattern
+            self.release_re_error = None;
+        } else if recompile {
+            match compile_filter(self.search_rels.text()) {
+                Ok(re) => {
+                    self.release_re = Some(re);
+                    self.release_re_error = None;
+                }
+                Err(e) => self.release_re_error = Some(format!("Release: {}", e)),
+            }
+        }
+        self.filter_releases();
+        self.selected_release = 0;
+        self.selected_asset = 0;
+        self.update_asset_re(false)?;
+        self.load_more_releases()
+    }
+    fn update_asset_re(&mut self, recompile: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if self.fuzzy {
+            self.asset_re_error = None;
+        } else if recompile {
+            match compile_filter(self.search_assets.text()) {
+                Ok(re) => {
+                    self.asset_re = Some(re);
+                    self.asset_re_error = None;
+                }
+                Err(e) => self.asset_re_error = Some(format!("Asset: {}", e)),
+            }
+        }
+        let assets = match self.found_releases.get(self.selected_release) {
+            Some(rel) => &rel.assets[..],
+            None => &[],
+        };
+        let found = if self.fuzzy {
+            common::find_asset_fuzzy(fuzzy_pattern(self.search_assets.text()), assets)
+        } else {
+            let found = match &self.asset_re {
+                Some(re) => common::find_asset_from(re, assets),
+                None => assets.to_vec(),
+            };
+            found.into_iter().map(|ass| (ass, Vec::new())).collect()
+        };
+        let (found, hits) = found.into_iter().unzip();
+        self.found_assets = found;
+        self.asset_hits = hits;
+        Ok(())
+    }
+
+    fn toggle_fuzzy(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.fuzzy = !self.fuzzy;
+        self.info(if self.fuzzy {
+            "Fuzzy filtering"
+        } else {
+            "Regex filtering"
+        });
+        self.update_release_re(true)?;
+        self.update_asset_re(true)
+    }
+
+    /// `HOST/OWNER/REPO` of the listed releases, for the state
+    fn listed_key(&self) -> Option<String> {
+        let (owner, repo) = &self.listed_repo;
+        let spec = format!("{}/{}", owner, repo).parse::<RepoSpec>().ok()?;
+        Some(spec.key())
+    }
+
+    /// Keep the Release and Asset fields with the listed repository
+    fn remember_filters(&mut self) {
+        let key = match self.listed_key() {
+            Some(key) => key,
+            None => return,
+        };
+        if let Some(fields) = self.state.repos.get_mut(&key) {
+            fields.release = self.search_rels.text().to_owned();
+            fields.asset = self.search_assets.text().to_owned();
+            fields.fuzzy = self.fuzzy;
+        }
+    }
+
+    /// Fill in the fields a repository was last used with and list its releases
+    fn restore(&mut self, fields: RepoFields) -> Result<(), Box<dyn std::error::Error>> {
+        // The filters being replaced belong to the repository listed so far
+        self.remember_filters();
+        self.owner.set(fields.owner);
+        self.repo.set(fields.repo);
+        self.search_rels.set(fields.release);
+        self.search_assets.set(fields.asset);
+        self.fuzzy = fields.fuzzy;
+        self.listed_repo = Default::default();
+        self.update_release_list()?;
+        self.update_release_re(true)?;
+        self.update_asset_re(true)?;
+        self.focus = Focus::Releases;
+        Ok(())
+    }
+
+    /// Start over with empty fields after [`Self::restore`] failed half way
+    fn restore_failed(&mut self) {
+        for i in 0..FIELD_NAMES.len() {
+            self.field_mut(i).set("");
+        }
+        self.fuzzy = false;
+        self.listed_repo = Default::default();
+        self.next_page = None;
+        self.release_re = None;
+        self.asset_re = None;
+        self.release_re_error = None;
+        self.asset_re_error = None;
+        self.focus = Focus::Fields;
+    }
+
+    fn toggle_favourite(&mut self) {
+        let key = match self.listed_key() {
+            Some(key) => key,
+            None => return self.error("No repository listed"),
+        };
+        let (owner, repo) = self.listed_repo.clone();
+        if self.state.toggle_favourite(&key) {
+            self.info(format!("Added {}/{} to the favourites", owner, repo));
+        } else {
+            self.info(format!("Removed {}/{} from the favourites", owner, repo));
+        }
+    }
+
+    /// Write the state file with the filters and the history of every field
+    fn save_state(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.remember_filters();
+        for (i, name) in FIELD_NAMES.iter().enumerate() {
+            let history = self.field_mut(i).history().to_vec();
+            self.state.history.insert(name.to_string(), history);
+        }
+        self.state.save()
+    }
+
+    /// Clicks focus and select what is under the pointer, the wheel scrolls it
+    fn on_mouse(&mut self, ev: MouseEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let (x, y) = (ev.column, ev.row);
+        let scroll = match ev.kind {
+            MouseEventKind::ScrollDown => 1,
+            MouseEventKind::ScrollUp => -1,
+            MouseEventKind::Down(MouseButton::Left) => 0,
+            _ => return Ok(()),
+        };
+        let step = |i: usize| (i as i64 + scroll).max(0) as usize;
+        if self.download_prompt.is_some() {
+            return Ok(());
+        }
+        if self.desc_fullscreen {
+            self.scroll_description(scroll as i32 * 3);
+            return Ok(());
+        }
+        if self.tab == Tab::Installed {
+            let area = self.installed_area;
+            let last = self.installed.len().saturating_sub(1);
+            if scroll != 0 {
+                self.installed_selected = step(self.installed_selected).min(last);
+            } else if let Some(tab) = tab_at(&self.tabs(), area, x, y) {
+                return self.switch_tab(tab);
+            } else if let Some(row) = table_row_at(area, self.installed_offset, x, y) {
+                self.installed_selected = row.min(last);
+            }
+            return Ok(());
+        }
+        if self.tab == Tab::Starred {
+            let area = self.starred_area;
+            let last = self.starred.len().saturating_sub(1);
+            if scroll != 0 {
+                self.starred_selected = step(self.starred_selected).min(last);
+            } else if let Some(tab) = tab_at(&self.tabs(), area, x, y) {
+                return self.switch_tab(tab);
+            } else if let Some(row) = table_row_at(area, self.starred_offset, x, y) {
+                self.starred_selected = row.min(last);
+            }
+            return Ok(());
+        }
+        let areas = match &self.areas {
+            Some(areas) => areas.clone(),
+            None => return Ok(()),
+        };
+        let hit = |area: Rect| contains(area, x, y);
+
+        if self.dropdown_open() && hit(areas.dropdown) {
+            let len = self.repo_candidates.len();
+            if scroll != 0 {
+                let i = self.candidate_selected.map_or(0, step);
+                return self.select_candidate(Some(i.min(len - 1)));
+            }
+            if let Some(i) = table_row_at(areas.dropdown, 0, x, y).filter(|&i| i < len) {
+                self.pick_candidate(i)?;
+            }
+            return Ok(());
+        }
+        if scroll != 0 {
+            if hit(areas.found_releases) {
+                self.focus = Focus::Releases;
+                self.move_selection(|i, _| step(i))?;
+            } else if hit(areas.found_assets) {
+                self.focus = Focus::Assets;
+                self.move_selection(|i, _| step(i))?;
+            } else if hit(areas.description) {
+                self.scroll_description(scroll as i32 * 3);
+            } else if hit(areas.jobs) {
+                self.job_selected = step(self.job_selected).min(self.jobs.len().saturating_sub(1));
+            }
+            return Ok(());
+        }
+
+        if let Some(tab) = tab_at(&self.tabs(), areas.top_area, x, y) {
+            return self.switch_tab(tab);
+        }
+        let fields = [
+            (areas.owner_key, areas.owner_field),
+            (areas.repo_key, areas.repo_field),
+            (areas.release_key, areas.release_field),
+            (areas.asset_key, areas.asset_field),
+        ];
+        if let Some(i) = fields
+            .iter()
+            .position(|&(key, field)| hit(key) || hit(field))
+        {
+            self.focus_field(i);
+        } else if hit(areas.found_releases) {
+            self.focus = Focus::Releases;
+            if let Some(row) = table_row_at(areas.found_releases, self.release_offset, x, y) {
+                self.move_selection(|_, _| row)?;
+            }
+        } else if hit(areas.found_assets) {
+            self.focus = Focus::Assets;
+            if let Some(row) = table_row_at(areas.found_assets, self.asset_offset, x, y) {
+                self.move_selection(|_, _| row)?;
+            }
+        } else if let Some(i) = areas.buttons.iter().position(|&b| hit(b)) {
+            self.focus = Focus::Buttons;
+            self.button_selected = i;
+            self.press(Button::ALL[i])?;
+        } else if hit(areas.jobs) && !self.jobs.is_empty() {
+            // Only the top border, the panel has no header
+            let first = (self.job_selected + 1).saturating_sub(JOB_ROWS);
+            let row = y.saturating_sub(areas.jobs.y + 1) as usize;
+            self.focus = Focus::Jobs;
+            self.job_selected = (first + row).min(self.jobs.len() - 1);
+        }
+        Ok(())
+    }
+
+    /// Keys go into the selected field, see `insert`
+    fn typing(&self) -> bool {
+        self.tab == Tab::Search
+            && !self.desc_fullscreen
+            && self.focus == Focus::Fields
+            && self.insert
+    }
+
+    /// Quit keys are typed into the fields like any other key, unless they cannot be typed
+    fn quits(&self, key: KeyEvent) -> bool {
+        self.keymap.is(key, Action::Quit)
+            && self.download_prompt.is_none()
+            && self.command_prompt.is_none()
+            && !(self.typing() && keymap::is_text(key))
+    }
+
+    /// Focus a text field, the keymap decides whether that starts insert mode
+    fn focus_field(&mut self, i: usize) {
+        self.field_selected = i;
+        self.focus = Focus::Fields;
+        self.insert = self.keymap.insert_on_focus;
+    }
+
+    /// Move between the fields and on to the other panes
+    fn move_field(&mut self, action: Action) {
+        match action {
+            Action::Left => self.field_selected = self.field_selected.saturating_sub(1),
+            Action::Next if self.field_selected == 3 => self.focus = Focus::Releases,
+            Action::Next | Action::Right => self.field_selected = self.field_selected.add(1).min(3),
+            Action::Prev if self.field_selected == 0 => {
+                self.focus = if self.jobs.is_empty() {
+                    Focus::Buttons
+                } else {
+                    Focus::Jobs
+                };
+            }
+            Action::Prev => self.field_selected -= 1,
+            Action::Up => {
+                self.field_selected = match self.field_selected {
+                    i @ (2 | 3) => i - 2,
+                    i => i,
+                };
+            }
+            Action::Down => match self.field_selected {
+                i @ (0 | 1) => self.field_selected = i + 2,
+                // The tables are below the second row of fields
+                2 => self.focus = Focus::Releases,
+                _ => self.focus = Focus::Assets,
+            },
+            _ => {}
+        }
+    }
+
+    fn on_global(&mut self, action: Action) -> Result<(), Box<dyn std::error::Error>> {
+        match action {
+            Action::Install => return self.press(Button::Install),
+            Action::Download => return self.press(Button::Download),
+            Action::Save => return self.press(Button::Save),
+            Action::CopyLink => return self.press(Button::Link),
+            Action::ToggleLog => self.show_log = !self.show_log,
+            Action::ToggleFuzzy => return self.toggle_fuzzy(),
+            Action::ScrollDown => self.scroll_description(1),
+            Action::ScrollUp => self.scroll_description(-1),
+            Action::Fullscreen => self.desc_fullscreen = true,
+            Action::InstalledTab => return self.show_installed(),
+            Action::StarredTab => return self.show_starred(),
+            Action::Favourite => self.toggle_favourite(),
+            Action::Changelog => return self.changelog_selected(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        if self.download_prompt.is_some() {
+            return self.on_prompt_key(key.code);
+        }
+        if self.command_prompt.is_some() {
+            return self.on_command_key(key.code);
+        }
+        if self.help_scroll.is_some() {
+            self.on_help_key(key);
+            return Ok(());
+        }
+        if !self.typing() || !keymap::is_text(key) {
+            match self.keymap.action(key, &[Action::Help, Action::Command]) {
+                Some(Action::Help) => {
+                    self.help_scroll = Some(0);
+                    return Ok(());
+                }
+                Some(Action::Command) => {
+                    self.command_prompt = Some(String::new());
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        if self.desc_fullscreen {
+            self.on_description_key(key);
+            return Ok(());
+        }
+        match self.tab {
+            Tab::Installed => return self.on_installed_key(key),
+            Tab::Starred => return self.on_starred_key(key),
+            Tab::Search => {}
+        }
+        let on_field = self.focus == Focus::Fields;
+        if on_field && !self.insert && self.keymap.is(key, Action::Insert) {
+            self.insert = true;
+            return Ok(());
+        }
+        // Keys that cannot be typed still work while typing, eg. F2
+        if !self.typing() || !keymap::is_text(key) {
+            if let Some(action) = self.keymap.action(key, &GLOBAL_ACTIONS) {
+                return self.on_global(action);
+            }
+        }
+        match self.focus {
+            Focus::Fields if self.insert => self.on_insert_key(key),
+            Focus::Fields => {
+                let actions = [
+                    Action::Left,
+                    Action::Right,
+                    Action::Up,
+                    Action::Down,
+                    Action::Next,
+                    Action::Prev,
+                ];
+                if let Some(action) = self.keymap.action(key, &actions) {
+                    self.move_field(action);
+                }
+                Ok(())
+            }
+            Focus::Buttons => self.on_button_key(key),
+            Focus::Jobs => {
+                self.on_jobs_key(key);
+                Ok(())
+            }
+            Focus::Releases | Focus::Assets => self.on_table_key(key),
+        }
+    }
+
+    /// A bracketed paste, on one line into the field or prompt being typed into
+    fn on_paste(&mut self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        // Terminals paste line breaks as `\r`
+        let line = text
+            .split(['\r', '\n'])
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .join(" ");
+        if let Some(prompt) = self
+            .download_prompt
+            .as_mut()
+            .or(self.command_prompt.as_mut())
+        {
+            prompt.push_str(&line);
+            return Ok(());
+        }
+        if self.help_scroll.is_some() || self.tab != Tab::Search || self.desc_fullscreen {
+            return Ok(());
+        }
+        // Pasting onto a field starts typing into it
+        if self.focus == Focus::Fields {
+            self.insert = true;
+            self.selected_field_mut().insert_str(&line);
+            return self.field_edited();
+        }
+        Ok(())
+    }
+
+    /// Keys typed into the selected field, Tab and Up/Down still move between the fields
+    /// and so do Left/Right at either end of it
+    fn on_insert_key(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        if self.dropdown_open() && self.on_dropdown_key(key.code)? {
+            return Ok(());
+        }
+        let before = self.selected_field().text().to_owned();
+        if self.selected_field_mut().on_key(key) {
+            if self.selected_field().text() != before {
+                self.field_edited()?;
+            }
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Enter => {
+                self.selected_field_mut().commit();
+                match self.field_selected {
+                    // Update the repo
+                    0 | 1 => {
+                        self.close_dropdown();
+                        self.expand_link();
+                        self.update_release_list()?
+                    }
+                    2 => self.update_release_re(true)?,
+                    3 => self.update_asset_re(true)?,
+                    _ => panic!("Invalid field"),
+                };
+            }
+            KeyCode::Esc => self.insert = false,
+            KeyCode::Left => self.move_field(Action::Left),
+            KeyCode::Right => self.move_field(Action::Right),
+            KeyCode::Up => self.move_field(Action::Up),
+            KeyCode::Down => self.move_field(Action::Down),
+            KeyCode::Tab => self.move_field(Action::Next),
+            KeyCode::BackTab => self.move_field(Action::Prev),
+            _ => {}
+        };
+        Ok(())
+    }
+
+    fn field_edited(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        match self.field_selected {
+            // Only Update the repo on Enter, until then search for it
+            0 | 1 => self.search_due = Some(Instant::now() + SEARCH_DELAY),
+            // Update the regexes
+            2 => self.update_release_re(true)?,
+            3 => self.update_asset_re(true)?,
+            _ => panic!("Invalid field"),
+        };
+        Ok(())
+    }
+
+    /// Run the repository search once the Owner and Repo fields were left alone long enough
+    fn on_search_due(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.search_due = None;
+        self.search_repos()
+    }
+
+    fn on_downloaded(&mut self, msg: Downloaded) -> Result<(), Box<dyn std::error::Error>> {
+        match msg {
+            Downloaded::ReleasePage {
+                owner,
+                repo,
+                page,
+                releases,
+            } => {
+                if (owner, repo) != self.listed_repo || Some(page) != self.next_page {
+                    return Ok(());
+                }
+                self.loading_releases = false;
+                match releases {
+                    Ok(releases) => self.on_release_page(page, releases)?,
+                    Err(e) => self.error(e),
+                }
+            }
+            Downloaded::Repos { query, repos } => {
+                if query != self.search_sent {
+                    return Ok(());
+                }
+                match repos {
+                    Ok(repos) => {
+                        self.repo_candidates = repos
+                            .into_iter()
+                            .map(|summary| Candidate {
+                                summary,
+                                remembered: None,
+                                latest: None,
+                                latest_requested: false,
+                            })
+                            .collect();
+                        self.suggest();
+                    }
+                    Err(e) => self.error(e),
+                }
+            }
+            Downloaded::LatestRelease { owner, repo, tag } => {
+                for row in &mut self.installed {
+                    if row.repo.as_ref() == Some(&(owner.clone(), repo.clone())) {
+                        row.latest = Some(tag.clone().ok().flatten());
+                    }
+                }
+                let tag = match tag {
+                    Ok(tag) => tag.unwrap_or_else(|| String::from("none")),
+                    Err(e) => {
+                        self.error(e);
+                        String::from("?")
+                    }
+                };
+                for c in &mut self.repo_candidates {
+                    if c.summary.owner == owner && c.summary.repo == repo {
+                        c.latest = Some(tag.clone());
+                    }
+                }
+            }
+            Downloaded::Starred { watched, repos } => {
+                if watched != self.starred_watched {
+                    return Ok(());
+                }
+                self.starred_loading = false;
+                self.starred_loaded = true;
+                match repos {
+                    Ok(repos) if repos.is_empty() => {
+                        self.info("No starred or watched repository has assets for this platform")
+                    }
+                    Ok(repos) => self.starred = repos,
+                    Err(e) => self.error(e),
+                }
+            }
+            Downloaded::Changelog(Ok(log)) => {
+                let title = format!("Changes {}...{}", log.from, log.to);
+                self.changelog = Some((title, markdown::render(&log.markdown(true))));
+                self.desc_fullscreen = true;
+                self.desc_scroll = 0;
+            }
+            Downloaded::Changelog(Err(e)) => self.error(e),
+            Downloaded::Started(id) => {
+                if let Some(job) = self.job_mut(id) {
+                    job.state = JobState::Running;
+                    job.started = Some(Instant::now());
+                }
+            }
+            Downloaded::Progress { job, done, total } => {
+                if let Some(job) = self.job_mut(job) {
+                    job.done = done;
+                    job.total = total;
+                }
+            }
+            Downloaded::Finished { job, message } => {
+                if let Some(job) = self.job_mut(job) {
+                    job.state = JobState::Finished(message.clone());
+                }
+                self.info(message);
+                if self.tab == Tab::Installed {
+                    self.refresh_installed()?;
+                }
+            }
+            Downloaded::Failed { job, error } => {
+                let cancelled = match self.job_mut(job) {
+                    Some(job) if job.handle.cancelled() => {
+                        job.state = JobState::Cancelled;
+                        true
+                    }
+                    Some(job) => {
+                        job.state = JobState::Failed(error.clone());
+                        false
+                    }
+                    None => false,
+                };
+                if cancelled {
+                    self.info(error);
+                } else {
+                    self.error(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn block(&self) -> tui::widgets::Block<'static> {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.theme.border)
+            .border_type(BorderType::Rounded)
+            .style(self.theme.background)
+    }
+}
+
+fn contains(area: Rect, x: u16, y: u16) -> bool {
+    area.x <= x && x < area.x + area.width && area.y <= y && y < area.y + area.height
+}
+
+/// The first visible row of a table after a draw, the way `tui` scrolls a `TableState`
+fn table_offset(offset: usize, selected: usize, rows: usize) -> usize {
+    if selected < offset {
+        selected
+    } else if selected >= offset + rows {
+        selected + 1 - rows
+    } else {
+        offset
+    }
+}
+
+/// The row of a bordered table with a header under `(x, y)`
+fn table_row_at(area: Rect, offset: usize, x: u16, y: u16) -> Option<usize> {
+    let inner = Rect {
+        y: area.y + 2,
+        height: area.height.saturating_sub(3),
+        ..area
+    };
+    contains(inner, x, y).then(|| offset + (y - inner.y) as usize)
+}
+
+/// The tab whose label in the top border is under `(x, y)`
+fn tab_at(tabs: &[(Tab, String)], area: Rect, x: u16, y: u16) -> Option<Tab> {
+    if y != area.y {
+        return None;
+    }
+    // Titles start after the corner of the border
+    let mut start = area.x + 1;
+    for (tab, label) in tabs {
+        let end = start + label.chars().count() as u16;
+        if (start..end).contains(&x) {
+            return Some(*tab);
+        }
+        start = end + 1;
+    }
+    None
+}
+
+/// The Owner or Repo field without the match-everything wildcards
+fn search_term(field: &str) -> String {
+    field
+        .trim_start_matches(".*")
+        .trim_end_matches(".*")
+        .trim_matches('*')
+        .to_owned()
+}
+
+/// Compile the Release or Asset field, an empty field or `*` matches everything.
+/// Only the last line of the error, the rest points into the pattern over several lines
+fn compile_filter(field: &str) -> Result<Regex, String> {
+    match field {
+        "" | "*" => Regex::new(".*"),
+        field => Regex::new(field),
+    }
+    .map_err(|e| e.to_string().lines().last().unwrap_or_default().to_owned())
+}
+
+/// The Release field is a plain regex or fuzzy pattern, unlike the `[RELEASE]` argument
+fn release_field_from_selector(selector: &str) -> String {
+    match selector.parse() {
+        Ok(ReleaseFinder::Latest) => String::new(),
+        Ok(ReleaseFinder::ByTag(tag)) => format!("^{}$", regex::escape(&tag)),
+        Ok(ReleaseFinder::Fuzzy(pattern)) => pattern,
+        _ => selector.to_owned(),
+    }
+}
+
+/// The Release or Asset field as a fuzzy pattern, the match-everything defaults are empty
+fn fuzzy_pattern(field: &str) -> &str {
+    field.trim_start_matches(".*").trim_matches('*')
+}
+
+/// `name` with the characters at `hits` highlighted
+fn highlight_hits(name: &str, hits: &[usize]) -> Spans<'static> {
+    let hit = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let spans = name
+        .chars()
+        .enumerate()
+        .group_by(|(i, _)| hits.contains(i))
+        .into_iter()
+        .map(|(is_hit, chars)| {
+            let text: String = chars.map(|(_, c)| c).collect();
+            if is_hit {
+                Span::styled(text, hit)
+            } else {
+                Span::raw(text)
+            }
+        })
+        .collect_vec();
+    Spans::from(spans)
+}
 
 pub fn tui(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    // A panic would otherwise leave the terminal in raw mode with the message lost
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+
     enable_raw_mode()?;
 
     let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // Pasted text arrives as one event instead of keys, a newline in it would press Enter
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
 
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
-    let (tx, rx) = input_handling_thread(&terminal);
-    let (send_repos, recv_rels) = downloading_thread(&terminal);
-    let mut app = TuiApp::new(args, send_repos, recv_rels);
+    // The forges' futures are not `Send`, lookups run as local tasks next to the event loop
+    let rt = Runtime::new()?;
+    let result = LocalSet::new().block_on(&rt, run(&mut terminal, args));
+    // The cancelled jobs stop at their next progress report, quitting doesn't wait for them
+    rt.shutdown_background();
+    restore_terminal();
+
+    for line in result? {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        cursor::Show
+    );
+}
+
+/// The event loop, returns the lines to print once the terminal is restored.
+/// It sleeps until a key, a mouse event, a worker's result or the repository search is due,
+/// and draws only after one of them
+async fn run(
+    terminal: &mut Terminal<impl Backend>,
+    args: Args,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut events = EventStream::new();
+    let (send_repos, mut recv_rels) = spawn_workers(Rc::new(forge::source));
+    let mut app = TuiApp::new(args, send_repos);
 
     terminal.clear()?;
 
     loop {
         terminal.draw(|f| app.draw(f))?;
 
-        match rx.recv()? {
-            Event::Tick => app.on_tick()?,
-            Event::Input(key) => match key.code {
-                KeyCode::Char('q') => {
-                    disable_raw_mode()?;
-                    execute!(
-                        terminal.backend_mut(),
-                        LeaveAlternateScreen,
-                        DisableMouseCapture
-                    )?;
-                    terminal.show_cursor()?;
-                    break;
-                }
-                code => app.on_key(code)?,
+        let search_due = app.search_due;
+        // Never polled without a search due, it only needs some instant
+        let search_at = tokio::time::Instant::from_std(search_due.unwrap_or_else(Instant::now));
+        let result = tokio::select! {
+            event = events.next() => match event {
+                // Some terminals report releases as well
+                Some(Ok(CEvent::Key(key))) if key.kind == KeyEventKind::Release => Ok(()),
+                Some(Ok(CEvent::Key(key))) if app.quits(key) => break,
+                Some(Ok(CEvent::Key(key))) => app.on_key(key),
+                Some(Ok(CEvent::Mouse(mouse))) => app.on_mouse(mouse),
+                Some(Ok(CEvent::Paste(text))) => app.on_paste(&text),
+                // Drawing again picks up the new size
+                Some(Ok(CEvent::Resize(..))) => Ok(()),
+                Some(Ok(CEvent::FocusGained)) | Some(Ok(CEvent::FocusLost)) => Ok(()),
+                Some(Err(e)) => return Err(e.into()),
+                None => break,
             },
+            Some(msg) = recv_rels.recv() => {
+                // Progress comes in bursts, take all of it before drawing once
+                let mut result = app.on_downloaded(msg);
+                while result.is_ok() {
+                    match recv_rels.try_recv() {
+                        Ok(msg) => result = app.on_downloaded(msg),
+                        Err(_) => break,
+                    }
+                }
+                result
+            }
+            _ = tokio::time::sleep_until(search_at), if search_due.is_some() => app.on_search_due(),
         };
+        if let Err(e) = result {
+            app.error(e.to_string());
+        }
+        if app.quit {
+            break;
+        }
     }
 
-    Ok(())
+    let cancelled = app.cancel_jobs();
+    if cancelled > 0 {
+        app.print_on_exit
+            .push(format!("Cancelled {} unfinished job(s)", cancelled));
+    }
+    if let Err(e) = app.save_state() {
+        app.print_on_exit
+            .push(format!("Could not save the TUI state: {}", e));
+    }
+    Ok(app.print_on_exit)
 }
 
-fn input_handling_thread(
-    _terminal: &Terminal<Backend>,
-) -> (Arc<Mutex<Sender<Event>>>, Receiver<Event>) {
-    let (tx, rx) = mpsc::channel();
-    let tx = Arc::new(Mutex::new(tx));
-
-    let tick_rate = Duration::from_millis(1000 / 60);
-    {
-        let tx = tx.clone();
-        thread::spawn(move || {
-            let mut last_tick = Instant::now();
-            loop {
-                // Poll for tick rate duration, if no events, sent tick event.
-                let timeout = tick_rate
-                    .checked_sub(last_tick.elapsed())
-                    .unwrap_or_else(|| Duration::from_secs(0));
-
-                // Poll for events
-                if event::poll(timeout).unwrap() {
-                    if let CEvent::Key(key) = event::read().unwrap() {
-                        tx.lock().unwrap().send(Event::Input(key)).unwrap();
-                    }
-                }
-
-                // Send tick event regularly
-                if last_tick.elapsed() >= tick_rate {
-                    tx.lock().unwrap().send(Event::Tick).unwrap();
-                    last_tick = Instant::now();
-                }
+/// Hand `text` to the first clipboard tool that is installed
+fn copy_to_clipboard(text: &str) -> bool {
+    let tools: [&[&str]; 5] = [
+        &["wl-copy"],
+        &["xclip", "-selection", "clipboard"],
+        &["xsel", "--clipboard", "--input"],
+        &["pbcopy"],
+        &["clip.exe"],
+    ];
+    tools.iter().any(|tool| {
+        let child = Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match child {
+            Ok(mut child) => {
+                let written = child
+                    .stdin
+                    .take()
+                    .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+                child.wait().is_ok_and(|status| status.success()) && written
             }
-        });
-    }
-
-    (tx, rx)
+            Err(_) => false,
+        }
+    })
 }
 
-// TODO: Cache the Release list in `~/.cache/grpm` and only download the new releases
-fn downloading_thread(
-    _terminal: &Terminal<Backend>,
-) -> (Sender<DownloadPlease>, Receiver<Vec<Release>>) {
-    let (send_repos, recv_repos) = mpsc::channel();
-    let (send_rels, recv_rels) = mpsc::channel();
-
-    thread::spawn(move || {
-        let rt = Runtime::new().unwrap();
-        use DownloadPlease::*;
-        while let Ok(req) = recv_repos.recv() {
+/// Lookups run at the same time, at most `LOOKUPS` of them. Installs and downloads are jobs,
+/// they run one after the other so that the panel's queue means something
+fn spawn_workers(
+    sources: Sources,
+) -> (
+    UnboundedSender<DownloadPlease>,
+    UnboundedReceiver<Downloaded>,
+) {
+    let (send_repos, mut recv_repos) = mpsc::unbounded_channel();
+    let (send_rels, recv_rels) = mpsc::unbounded_channel();
+    let (send_jobs, recv_jobs) = mpsc::unbounded_channel();
+
+    // Unpacking and install commands block, so the job worker gets a thread of its own
+    let handle = Handle::current();
+    let tx = send_rels.clone();
+    task::spawn_blocking(move || run_jobs(handle, recv_jobs, tx));
+
+    task::spawn_local(async move {
+        let lookups = Rc::new(Semaphore::new(LOOKUPS));
+        while let Some(req) = recv_repos.recv().await {
             match req {
-                Releases(owner, repo) => {
-                    let rels = rt.block_on(common::list_releases(&owner, &repo)).unwrap();
-                    send_rels.send(rels).unwrap();
+                DownloadPlease::Install(..) | DownloadPlease::Download(..) => {
+                    let _ = send_jobs.send(req);
                 }
-                Asset(ass) => {
-                    // TODO: Download the asset
+                req => {
+                    let (lookups, tx) = (lookups.clone(), send_rels.clone());
+                    let sources = sources.clone();
+                    task::spawn_local(async move {
+                        let _permit = lookups.acquire().await;
+                        let _ = tx.send(lookup(req, &sources).await);
+                    });
                 }
             }
         }
@@ -588,3 +3372,99 @@ fn downloading_thread(
 
     (send_repos, recv_rels)
 }
+
+// TODO: Cache the Release list in `~/.cache/grpm` and only download the new releases
+async fn lookup(req: DownloadPlease, sources: &Sources) -> Downloaded {
+    use DownloadPlease::*;
+    match req {
+        ReleasePage { owner, repo, page } => {
+            let releases = async {
+                sources(&owner, &repo)?
+                    .list_releases_page(page, RELEASE_PAGE)
+                    .await
+            }
+            .await
+            .map_err(|e| format!("Failed to list the releases of {}/{}: {}", owner, repo, e));
+            Downloaded::ReleasePage {
+                owner,
+                repo,
+                page,
+                releases,
+            }
+        }
+        SearchRepos(query) => {
+            let repos = GitHub::search(&query, SEARCH_RESULTS)
+                .await
+                .map_err(|e| format!("Failed to search for {}: {}", query, e));
+            Downloaded::Repos { query, repos }
+        }
+        LatestRelease { owner, repo } => {
+            let tag = async { ReleaseFinder::Latest.find(&*sources(&owner, &repo)?).await }
+                .await
+                .map(|rel| rel.map(|rel| rel.tag_name))
+                .map_err(|e| {
+                    format!(
+                        "Failed to get the latest release of {}/{}: {}",
+                        owner, repo, e
+                    )
+                });
+            Downloaded::LatestRelease { owner, repo, tag }
+        }
+        Starred { watched } => {
+            let repos = commands::installable(watched)
+                .await
+                .map_err(|e| format!("Failed to list the repositories: {}", e));
+            Downloaded::Starred { watched, repos }
+        }
+        Changelog { repo, from, to } => {
+            let log = commands::changelog(&repo, &from, to.as_deref())
+                .await
+                .map_err(|e| format!("Failed to get the changelog of {}: {}", repo, e));
+            Downloaded::Changelog(log)
+        }
+        Install(..) | Download(..) => unreachable!("jobs go to the job worker"),
+    }
+}
+
+fn run_jobs(
+    handle: Handle,
+    mut recv_jobs: UnboundedReceiver<DownloadPlease>,
+    send_rels: UnboundedSender<Downloaded>,
+) {
+    use DownloadPlease::*;
+    while let Some(req) = recv_jobs.blocking_recv() {
+        let (job, result) = match req {
+            Install(job, name, pkg) => {
+                let result = if job.cancelled() {
+                    Err(format!("Cancelled installing {}", name))
+                } else {
+                    let _ = send_rels.send(Downloaded::Started(job.id));
+                    let progress = job.progress(send_rels.clone());
+                    handle
+                        .block_on(commands::install(&name, &pkg, progress))
+                        .map(|tag| format!("Installed {} {}", name, tag))
+                        .map_err(|e| format!("Failed to install {}: {}", name, e))
+                };
+                (job, result)
+            }
+            Download(job, repo, ass, path) => {
+                let result = if job.cancelled() {
+                    Err(format!("Cancelled downloading {}", ass.name))
+                } else {
+                    let _ = send_rels.send(Downloaded::Started(job.id));
+                    let progress = job.progress(send_rels.clone());
+                    handle
+                        .block_on(commands::download(&repo, &ass, &path, progress))
+                        .map(|path| format!("Downloaded {}", path.display()))
+                        .map_err(|e| format!("Failed to download {}: {}", ass.name, e))
+                };
+                (job, result)
+            }
+            _ => continue,
+        };
+        let _ = send_rels.send(job.report(result));
+    }
+}
+
+#[cfg(test)]
+mod tests;
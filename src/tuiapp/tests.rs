@@ -0,0 +1,330 @@
+//! The TUI driven by key events against a `TestBackend`, with canned releases instead of a forge
+use async_trait::async_trait;
+use std::{env, fs};
+use tui::backend::TestBackend;
+
+use super::*;
+use crate::keymap::parse_key;
+
+/// Releases of every repository, or the error every request fails with
+struct FakeSource(Result<Vec<Release>, String>);
+
+#[async_trait(?Send)]
+impl ReleaseSource for FakeSource {
+    async fn list_releases_page(&self, page: u32, per: u8) -> Result<Vec<Release>, Error> {
+        let rels = self.0.as_ref().map_err(|e| e.as_str())?;
+        let start = (page as usize - 1) * per as usize;
+        Ok(rels
+            .iter()
+            .skip(start)
+            .take(per as usize)
+            .cloned()
+            .collect())
+    }
+    async fn get_latest(&self) -> Result<Option<Release>, Error> {
+        let rels = self.0.as_ref().map_err(|e| e.as_str())?;
+        Ok(rels.iter().find(|rel| !rel.prerelease).cloned())
+    }
+    async fn get_by_tag(&self, tag: &str) -> Result<Option<Release>, Error> {
+        let rels = self.0.as_ref().map_err(|e| e.as_str())?;
+        Ok(rels.iter().find(|rel| rel.tag_name == tag).cloned())
+    }
+    async fn get_asset(&self, id: forge::AssetId) -> Result<Option<Asset>, Error> {
+        forge::find_asset_by_id(self, id).await
+    }
+}
+
+fn release(tag: &str, body: &str, assets: &[&str]) -> Release {
+    Release {
+        tag_name: tag.to_owned(),
+        name: None,
+        body: Some(body.to_owned()),
+        draft: false,
+        prerelease: false,
+        published_at: None,
+        html_url: None,
+        assets: assets
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Asset {
+                id: i as u64,
+                name: name.to_string(),
+                label: None,
+                content_type: None,
+                size: Some(1024),
+                download_count: None,
+                uploader: None,
+                created_at: None,
+                updated_at: None,
+                browser_download_url: format!("https://example.com/{}/{}", tag, name)
+                    .parse()
+                    .unwrap(),
+            })
+            .collect(),
+    }
+}
+
+fn fixtures() -> Vec<Release> {
+    vec![
+        release(
+            "v1.1.0",
+            "Faster *everything*",
+            &["tool-linux.tar.gz", "tool-macos.tar.gz"],
+        ),
+        release("v1.0.0", "First release", &["tool-v1-linux.tar.gz"]),
+    ]
+}
+
+struct Harness {
+    app: TuiApp,
+    requests: UnboundedReceiver<DownloadPlease>,
+    sources: Sources,
+    terminal: Terminal<TestBackend>,
+}
+
+impl Harness {
+    /// The TUI opened on `owner/repo` with the default keys, nothing is read from the config or state
+    fn new(releases: Result<Vec<Release>, String>) -> Self {
+        let (tx, requests) = mpsc::unbounded_channel();
+        let args = Args {
+            owner: Some(String::from("owner")),
+            repo: Some(String::from("repo")),
+            ..Args::default()
+        };
+        let app = TuiApp::with(args, tx, &TuiConfig::default(), State::default());
+        let sources: Sources = Rc::new(move |_: &str, _: &str| {
+            Ok(Box::new(FakeSource(releases.clone())) as Box<dyn ReleaseSource>)
+        });
+        let terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        let mut harness = Self {
+            app,
+            requests,
+            sources,
+            terminal,
+        };
+        harness.settle();
+        harness
+    }
+
+    /// Answer every request made so far, searches find nothing
+    fn settle(&mut self) {
+        while let Ok(req) = self.requests.try_recv() {
+            let msg = match req {
+                DownloadPlease::SearchRepos(query) => Downloaded::Repos {
+                    query,
+                    repos: Ok(Vec::new()),
+                },
+                req => futures::executor::block_on(lookup(req, &self.sources)),
+            };
+            self.app.on_downloaded(msg).unwrap();
+        }
+    }
+
+    /// Whitespace separated keys as in `[tui.keys]`, then whatever they requested is answered
+    fn keys(&mut self, keys: &str) {
+        for key in keys.split_whitespace() {
+            self.app.on_key(parse_key(key).unwrap()).unwrap();
+        }
+        self.settle();
+    }
+    fn paste(&mut self, text: &str) {
+        self.app.on_paste(text).unwrap();
+        self.settle();
+    }
+    fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            self.app.on_key(KeyEvent::from(KeyCode::Char(c))).unwrap();
+        }
+        self.settle();
+    }
+
+    /// The rows of the screen
+    fn draw(&mut self) -> Vec<String> {
+        let app = &mut self.app;
+        self.terminal.draw(|f| app.draw(f)).unwrap();
+        let buf = self.terminal.backend().buffer();
+        buf.content
+            .chunks(buf.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol.as_str()).collect())
+            .collect()
+    }
+    fn screen(&mut self) -> String {
+        self.draw().join("\n")
+    }
+
+    fn found_tags(&self) -> Vec<&str> {
+        let rels = self.app.found_releases.iter();
+        rels.map(|rel| rel.tag_name.as_str()).collect()
+    }
+}
+
+#[test]
+fn lists_the_releases_of_the_repository() {
+    let mut h = Harness::new(Ok(fixtures()));
+    assert_eq!(
+        h.app.listed_repo,
+        (String::from("owner"), String::from("repo"))
+    );
+    assert_eq!(h.found_tags(), ["v1.1.0", "v1.0.0"]);
+    let screen = h.screen();
+    assert!(screen.contains("v1.1.0"), "{}", screen);
+    assert!(screen.contains("v1.0.0"), "{}", screen);
+    // The newest release is selected, with its assets and notes
+    assert!(screen.contains("tool-macos.tar.gz"), "{}", screen);
+    assert!(screen.contains("Faster everything"), "{}", screen);
+}
+
+#[test]
+fn the_release_field_filters_the_releases() {
+    let mut h = Harness::new(Ok(fixtures()));
+    h.keys("tab tab");
+    assert_eq!((h.app.focus, h.app.field_selected), (Focus::Fields, 2));
+    h.type_text(r"^v1\.0");
+    assert_eq!(h.found_tags(), ["v1.0.0"]);
+    let screen = h.screen();
+    assert!(screen.contains("tool-v1-linux.tar.gz"), "{}", screen);
+    assert!(!screen.contains("tool-macos.tar.gz"), "{}", screen);
+}
+
+#[test]
+fn a_paste_is_one_edit_and_its_newline_is_not_enter() {
+    let mut h = Harness::new(Ok(fixtures()));
+    h.keys("tab tab");
+    h.paste("^v1\\.0\r\n");
+    assert_eq!(h.app.search_rels.text(), r"^v1\.0");
+    assert_eq!(h.found_tags(), ["v1.0.0"]);
+    // Enter would have put it into the history
+    assert!(h.app.search_rels.history().is_empty());
+}
+
+#[test]
+fn an_invalid_release_regex_keeps_the_list_and_says_why() {
+    let mut h = Harness::new(Ok(fixtures()));
+    h.keys("tab tab");
+    h.type_text("(");
+    assert!(h.app.release_re_error.is_some());
+    assert_eq!(h.found_tags(), ["v1.1.0", "v1.0.0"]);
+}
+
+#[test]
+fn moving_through_the_releases_shows_their_assets() {
+    let mut h = Harness::new(Ok(fixtures()));
+    h.keys("tab tab tab tab");
+    assert_eq!(h.app.focus, Focus::Releases);
+    h.keys("down");
+    assert_eq!(h.app.selected_release, 1);
+    let screen = h.screen();
+    assert!(screen.contains("First release"), "{}", screen);
+    assert!(!screen.contains("tool-macos.tar.gz"), "{}", screen);
+
+    h.keys("enter");
+    assert_eq!(h.app.focus, Focus::Assets);
+    let name = h.app.selected_asset().map(|ass| ass.name.as_str());
+    assert_eq!(name, Some("tool-v1-linux.tar.gz"));
+}
+
+#[test]
+fn scrolling_to_the_end_loads_the_next_page() {
+    let many = (0..45)
+        .rev()
+        .map(|i| release(&format!("v0.{}.0", i), "", &[]))
+        .collect::<Vec<_>>();
+    let mut h = Harness::new(Ok(many));
+    assert_eq!(h.app.all_releases.len(), RELEASE_PAGE as usize);
+    h.keys("tab tab tab tab end");
+    assert_eq!(h.app.all_releases.len(), 45);
+    // Only an empty page is the last one
+    assert_eq!(h.app.next_page, Some(3));
+    h.keys("end");
+    assert_eq!(h.app.all_releases.len(), 45);
+    assert_eq!(h.app.next_page, None);
+}
+
+#[test]
+fn a_failing_source_shows_the_error() {
+    let mut h = Harness::new(Err(String::from("API rate limit exceeded")));
+    assert!(h.found_tags().is_empty());
+    let screen = h.screen();
+    assert!(
+        screen.contains("Failed to list the releases of owner/repo: API rate limit exceeded"),
+        "{}",
+        screen
+    );
+}
+
+#[test]
+fn a_last_search_that_fails_to_restore_starts_empty() {
+    let mut state = State::default();
+    state.used("github.com/owner/repo", "owner", "repo");
+    // Nothing takes the release list request
+    let (tx, _) = mpsc::unbounded_channel();
+    let app = TuiApp::with(Args::default(), tx, &TuiConfig::default(), state);
+    assert_eq!(app.owner.text(), "");
+    assert_eq!(app.repo.text(), "");
+    assert_eq!(app.focus, Focus::Fields);
+    assert!(app.log[0]
+        .text
+        .starts_with("Could not restore the last search"));
+}
+
+#[test]
+fn install_and_save_use_the_listed_repository_not_the_edited_fields() {
+    let mut h = Harness::new(Ok(fixtures()));
+    // Typed over but not listed yet
+    h.app.owner.set("other");
+    h.app.repo.set("thing");
+
+    h.app.press(Button::Install).unwrap();
+    match h.requests.try_recv() {
+        Ok(DownloadPlease::Install(_, name, pkg)) => {
+            assert_eq!(name, "repo");
+            assert_eq!(pkg.repo, "owner/repo");
+            assert_eq!(pkg.release.as_deref(), Some("t:v1.1.0"));
+        }
+        req => panic!("{:?}", req),
+    }
+
+    let path = env::temp_dir().join(format!("grpm-save-{}.toml", std::process::id()));
+    h.app.save(Some(&path)).unwrap();
+    let saved = Config::load(&path).unwrap().packages;
+    fs::remove_file(&path).unwrap();
+    assert_eq!(saved.keys().collect::<Vec<_>>(), ["repo"]);
+    assert_eq!(saved["repo"].repo, "owner/repo");
+}
+
+#[test]
+fn quitting_cancels_the_unfinished_jobs() {
+    let mut h = Harness::new(Ok(fixtures()));
+    let pkg = Package {
+        repo: String::from("owner/repo"),
+        release: None,
+        asset: None,
+        install: None,
+        bin: Vec::new(),
+    };
+    for name in ["done", "running", "queued"] {
+        let (name, pkg) = (String::from(name), pkg.clone());
+        let title = format!("Install {}", name);
+        h.app
+            .queue_job(title, |job| DownloadPlease::Install(job, name, pkg))
+            .unwrap();
+    }
+    // Nothing runs the jobs, the worker's reports are made up
+    let report = h.app.jobs[0].handle.report(Ok(String::from("Installed")));
+    h.app.on_downloaded(report).unwrap();
+    h.app.on_downloaded(Downloaded::Started(1)).unwrap();
+    assert_eq!(h.app.cancel_jobs(), 2);
+    let cancelled = h.app.jobs.iter().map(|job| job.handle.cancelled());
+    assert_eq!(cancelled.collect::<Vec<_>>(), [false, true, true]);
+}
+
+#[test]
+fn the_help_overlay_lists_the_bindings() {
+    let mut h = Harness::new(Ok(fixtures()));
+    h.keys("esc ?");
+    assert_eq!(h.app.help_scroll, Some(0));
+    let screen = h.screen();
+    assert!(screen.contains("toggle-fuzzy"), "{}", screen);
+    h.keys("esc");
+    assert_eq!(h.app.help_scroll, None);
+}